@@ -0,0 +1,255 @@
+use serde::Serialize;
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    engine::{FunctionRunner, RunOptions},
+    error::Error,
+    function_run_result::{FunctionOutcome, FunctionOutput, FunctionRunResult},
+};
+
+/// An input to run as part of a batch.
+pub struct BatchCase {
+    pub name: String,
+    pub input: Vec<u8>,
+    pub scale_factor: f64,
+}
+
+/// The result of running a single [`BatchCase`], or the error preventing it from running.
+pub struct BatchEntry {
+    pub name: String,
+    pub result: Result<FunctionRunResult, Error>,
+    /// Wall-clock time spent running the case.
+    pub duration: Duration,
+}
+
+#[derive(Serialize)]
+struct BatchEntryJson<'a> {
+    name: &'a str,
+    passed: bool,
+    duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<&'a FunctionRunResult>,
+}
+
+impl BatchEntry {
+    /// Whether the Function succeeded, produced valid output and stayed within its resource limits.
+    pub fn passed(&self) -> bool {
+        self.failure_reason().is_none()
+    }
+
+    pub fn failure_reason(&self) -> Option<String> {
+        let result = match &self.result {
+            Ok(result) => result,
+            Err(error) => return Some(error.to_string()),
+        };
+
+        if result.outcome != FunctionOutcome::Success {
+            return Some(result.outcome.to_string());
+        }
+        if let FunctionOutput::InvalidJsonOutput(_) = result.output {
+            return Some("invalid output".to_string());
+        }
+        if result.exceeds_limits() {
+            return Some("exceeds limits".to_string());
+        }
+
+        None
+    }
+
+    pub fn duration_ms(&self) -> f64 {
+        self.duration.as_secs_f64() * 1000.0
+    }
+
+    /// The entry as a single line of JSON.
+    pub fn to_ndjson(&self) -> String {
+        let (error, result) = match &self.result {
+            Ok(result) => (None, Some(result)),
+            Err(error) => (Some(error.to_string()), None),
+        };
+
+        serde_json::to_string(&BatchEntryJson {
+            name: &self.name,
+            passed: self.passed(),
+            duration_ms: self.duration_ms(),
+            error,
+            result,
+        })
+        .unwrap_or_else(|error| error.to_string())
+    }
+}
+
+/// Run every case against the export, using up to `jobs` threads. Entries are returned in the order of `cases`.
+pub fn run_batch(
+    runner: &FunctionRunner,
+    export: &str,
+    cases: Vec<BatchCase>,
+    options: &RunOptions,
+    jobs: usize,
+) -> Vec<BatchEntry> {
+    let next_case = AtomicUsize::new(0);
+    let run_cases = || {
+        let mut entries = Vec::new();
+        loop {
+            let index = next_case.fetch_add(1, Ordering::Relaxed);
+            let Some(case) = cases.get(index) else {
+                return entries;
+            };
+
+            let options = RunOptions {
+                scale_factor: case.scale_factor,
+                ..options.clone()
+            };
+            let start = Instant::now();
+            let result = runner.run_with_options(case.input.clone(), export, &options);
+            entries.push((
+                index,
+                BatchEntry {
+                    name: case.name.clone(),
+                    result,
+                    duration: start.elapsed(),
+                },
+            ));
+        }
+    };
+
+    let mut entries: Vec<(usize, BatchEntry)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..jobs.max(1)).map(|_| scope.spawn(run_cases)).collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Batch worker panicked"))
+            .collect()
+    });
+    entries.sort_by_key(|(index, _)| *index);
+
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// A table with one row per entry, followed by the number of passing entries.
+pub fn summary_table(entries: &[BatchEntry]) -> String {
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.name.len())
+        .chain(std::iter::once("Input".len()))
+        .max()
+        .unwrap_or_default();
+
+    let mut table = String::new();
+    let _ = writeln!(
+        table,
+        "{:<name_width$}  {:>12}  {:>10}  {:>10}  {:>11}  {:>10}  Status",
+        "Input", "Instructions", "Memory", "Input Size", "Output Size", "Duration",
+    );
+
+    for entry in entries {
+        let status = match entry.failure_reason() {
+            None => "pass".to_string(),
+            Some(reason) => format!("FAIL ({reason})"),
+        };
+
+        let _ = match &entry.result {
+            Ok(result) => writeln!(
+                table,
+                "{:<name_width$}  {:>12}  {:>8}KB  {:>9}B  {:>10}B  {:>8.2}ms  {status}",
+                entry.name,
+                result.instructions,
+                result.memory_usage,
+                result.input_size(),
+                result.output_size(),
+                entry.duration_ms(),
+            ),
+            Err(_) => writeln!(
+                table,
+                "{:<name_width$}  {:>12}  {:>10}  {:>10}  {:>11}  {:>8.2}ms  {status}",
+                entry.name,
+                "-",
+                "-",
+                "-",
+                "-",
+                entry.duration_ms(),
+            ),
+        };
+    }
+
+    let passed = entries.iter().filter(|entry| entry.passed()).count();
+    let _ = write!(table, "\n{passed}/{} passed", entries.len());
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    fn exit_code_case(name: &str, code: i32) -> BatchCase {
+        BatchCase {
+            name: name.to_string(),
+            input: json!({ "code": code }).to_string().into(),
+            scale_factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_run_batch_keeps_case_order() -> Result<()> {
+        let runner = FunctionRunner::from_path("tests/fixtures/build/exit_code.wasm", None)?;
+        let cases = (0..6)
+            .map(|index| exit_code_case(&format!("case{index}"), index % 2))
+            .collect();
+
+        let entries = run_batch(&runner, "_start", cases, &RunOptions::default(), 3);
+
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["case0", "case1", "case2", "case3", "case4", "case5"]
+        );
+        let passed: Vec<bool> = entries.iter().map(BatchEntry::passed).collect();
+        assert_eq!(passed, [true, false, true, false, true, false]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_table() -> Result<()> {
+        let runner = FunctionRunner::from_path("tests/fixtures/build/exit_code.wasm", None)?;
+        let mut entries = run_batch(
+            &runner,
+            "_start",
+            vec![exit_code_case("ok.json", 0)],
+            &RunOptions::default(),
+            1,
+        );
+        entries.push(BatchEntry {
+            name: "broken.json".to_string(),
+            result: Err(Error::InvalidInput {
+                message: "Invalid input JSON".to_string(),
+            }),
+            duration: Duration::from_millis(5),
+        });
+
+        let table = summary_table(&entries);
+        assert!(table.contains("ok.json"), "{table}");
+        assert!(table.contains("pass"), "{table}");
+        assert!(
+            table.contains("5.00ms  FAIL (Invalid input JSON)"),
+            "{table}"
+        );
+        assert!(table.ends_with("1/2 passed"), "{table}");
+
+        let line: serde_json::Value = serde_json::from_str(&entries[1].to_ndjson())?;
+        assert_eq!(
+            line,
+            json!({"name": "broken.json", "passed": false, "duration_ms": 5.0, "error": "Invalid input JSON"})
+        );
+
+        Ok(())
+    }
+}
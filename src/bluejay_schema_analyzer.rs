@@ -1,12 +1,13 @@
-use crate::scale_limits_analyzer::ScaleLimitsAnalyzer;
-use anyhow::{anyhow, Result};
+use crate::{
+    error::Error, limit_profile::LimitProfile, scale_limits_analyzer::UnclampedScaleLimitsAnalyzer,
+};
 use bluejay_parser::{
     ast::{
         definition::{DefinitionDocument, SchemaDefinition},
         executable::ExecutableDocument,
         Parse,
     },
-    Error,
+    Error as ParserError,
 };
 
 pub struct BluejaySchemaAnalyzer;
@@ -18,20 +19,33 @@ impl BluejaySchemaAnalyzer {
         query: &str,
         query_path: Option<&str>,
         input: &serde_json::Value,
-    ) -> Result<f64> {
-        let document_definition = DefinitionDocument::parse(schema_string)
-            .map_err(|errors| anyhow!(Error::format_errors(schema_string, schema_path, errors)))?;
+        limit_profile: &LimitProfile,
+    ) -> Result<f64, Error> {
+        let document_definition = DefinitionDocument::parse(schema_string).map_err(|errors| {
+            Error::SchemaParse(ParserError::format_errors(
+                schema_string,
+                schema_path,
+                errors,
+            ))
+        })?;
 
-        let schema_definition = SchemaDefinition::try_from(&document_definition)
-            .map_err(|errors| anyhow!(Error::format_errors(schema_string, schema_path, errors)))?;
+        let schema_definition =
+            SchemaDefinition::try_from(&document_definition).map_err(|errors| {
+                Error::SchemaParse(ParserError::format_errors(
+                    schema_string,
+                    schema_path,
+                    errors,
+                ))
+            })?;
 
-        let executable_document = ExecutableDocument::parse(query)
-            .map_err(|errors| anyhow!(Error::format_errors(query, query_path, errors)))?;
+        let executable_document = ExecutableDocument::parse(query).map_err(|errors| {
+            Error::QueryParse(ParserError::format_errors(query, query_path, errors))
+        })?;
 
         let cache =
             bluejay_validator::executable::Cache::new(&executable_document, &schema_definition);
 
-        ScaleLimitsAnalyzer::analyze(
+        UnclampedScaleLimitsAnalyzer::analyze(
             &executable_document,
             &schema_definition,
             None,
@@ -39,13 +53,15 @@ impl BluejaySchemaAnalyzer {
             &cache,
             input,
         )
-        .map_err(|e| anyhow!("Unable to analyze scale limits: {}", e.message()))
+        .map(|scale_factor| limit_profile.clamp_scale_factor(scale_factor))
+        .map_err(|e| Error::ScaleLimits(e.message().to_string()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scale_limits_analyzer::ScaleLimitsAnalyzer;
     use serde_json::json;
 
     #[test]
@@ -67,6 +83,7 @@ mod tests {
             query,
             Some("query.graphql"),
             &input_json,
+            &LimitProfile::default(),
         );
         assert!(
             result.is_ok(),
@@ -101,6 +118,7 @@ mod tests {
             query,
             Some("query.graphql"),
             &input_json,
+            &LimitProfile::default(),
         );
         assert!(
             result.is_ok(),
@@ -135,6 +153,7 @@ mod tests {
             query,
             Some("query.graphql"),
             &input_json,
+            &LimitProfile::default(),
         );
         assert!(
             result.is_ok(),
@@ -150,6 +169,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_analyze_schema_with_limit_profile_scale_factor_bounds() {
+        let schema_string = r#"
+            directive @scaleLimits(rate: Float!) on FIELD_DEFINITION
+            type Query {
+                cartLines: [String] @scaleLimits(rate: 0.005)
+            }
+        "#;
+        let query = "{ cartLines }";
+        let limit_profile = LimitProfile {
+            min_scale_factor: 1.5,
+            max_scale_factor: 2.0,
+            ..LimitProfile::default()
+        };
+
+        let scale_factor = |lines: usize| {
+            BluejaySchemaAnalyzer::analyze_schema_definition(
+                schema_string,
+                None,
+                query,
+                None,
+                &json!({ "cartLines": vec!["item"; lines] }),
+                &limit_profile,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(scale_factor(1), 1.5);
+        assert_eq!(scale_factor(500), 2.0);
+    }
+
+    #[test]
+    fn test_scale_limits_analyzer_is_clamped() {
+        let schema_string = r#"
+            directive @scaleLimits(rate: Float!) on FIELD_DEFINITION
+            type Query {
+                cartLines: [String] @scaleLimits(rate: 0.005)
+                shop: String
+            }
+        "#;
+        let document_definition = DefinitionDocument::parse(schema_string).unwrap();
+        let schema_definition = SchemaDefinition::try_from(&document_definition).unwrap();
+        let executable_document = ExecutableDocument::parse("{ shop }").unwrap();
+        let cache =
+            bluejay_validator::executable::Cache::new(&executable_document, &schema_definition);
+        let input = json!({ "shop": "a" });
+        let variable_values = Default::default();
+
+        let scale_factor = ScaleLimitsAnalyzer::analyze(
+            &executable_document,
+            &schema_definition,
+            None,
+            &variable_values,
+            &cache,
+            &input,
+        )
+        .unwrap();
+        assert_eq!(scale_factor, 1.0);
+
+        let unclamped_scale_factor = UnclampedScaleLimitsAnalyzer::analyze(
+            &executable_document,
+            &schema_definition,
+            None,
+            &variable_values,
+            &cache,
+            &input,
+        )
+        .unwrap();
+        assert_eq!(unclamped_scale_factor, 0.0);
+    }
+
     #[test]
     fn test_invalid_schema() {
         let invalid_schema_string = r#"
@@ -169,6 +259,7 @@ mod tests {
             valid_query,
             Some("query.graphql"),
             &input_json,
+            &LimitProfile::default(),
         );
 
         assert!(
@@ -197,6 +288,7 @@ mod tests {
             invalid_query,
             Some("invalid_query.graphql"),
             &input_json,
+            &LimitProfile::default(),
         );
 
         assert!(
@@ -225,6 +317,7 @@ mod tests {
             query,
             Some("query.graphql"),
             &input_json,
+            &LimitProfile::default(),
         );
         assert!(
             result.is_ok(),
@@ -264,6 +357,7 @@ mod tests {
             query,
             Some("query.graphql"),
             &input_json,
+            &LimitProfile::default(),
         );
         assert!(
             result.is_ok(),
@@ -0,0 +1,375 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fmt;
+use wasmparser::{Parser, Payload};
+
+use crate::module_info::ModuleInfo;
+
+/// Input prepared for the Function by a [`Codec`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncodedInput {
+    /// The bytes passed to the Function on stdin.
+    pub bytes: Vec<u8>,
+    /// The input as JSON, used to compute the scale factor. `None` when the input isn't JSON.
+    pub value: Option<serde_json::Value>,
+}
+
+/// Why bytes couldn't be decoded, and the byte offset where decoding failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} (at byte {})", self.message, self.offset)
+    }
+}
+
+/// How a Function's input is encoded before running it, and how its output is decoded to JSON for the report.
+pub trait Codec: Send + Sync {
+    /// Name reported in the run result, e.g. `json`.
+    fn name(&self) -> &'static str;
+
+    /// Encode an input file into the bytes passed to the Function.
+    fn encode_input(&self, input: Vec<u8>) -> Result<EncodedInput>;
+
+    /// Decode bytes written in this encoding to JSON.
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError>;
+
+    /// Size of encoded bytes as counted against the input and output limits.
+    fn size(&self, bytes: &[u8]) -> usize {
+        bytes.len()
+    }
+
+    /// Whether encoded bytes are binary, they are shown as hex rather than text when they can't be decoded.
+    fn is_binary(&self) -> bool {
+        false
+    }
+
+    /// The codec to decode these bytes with when this one detects their encoding, `None` to decode them with this
+    /// codec.
+    fn detect(&self, _bytes: &[u8]) -> Option<&'static dyn Codec> {
+        None
+    }
+}
+
+/// JSON, passed to the Function minified.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+/// Input passed to the Function as-is, it and the output are shown as JSON when they are.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RawCodec;
+
+/// MessagePack, converted from a JSON input file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+/// Detects the encoding of each output from its bytes, and encodes inputs as the module hints it expects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AutoCodec {
+    /// Whether the module embeds a MessagePack library.
+    pub messagepack: bool,
+}
+
+/// Names of MessagePack libraries, as they appear in the symbols of modules using them.
+const MESSAGEPACK_HINTS: [&str; 4] = ["rmp-serde", "rmp_serde", "msgpack", "messagepack"];
+
+/// Custom sections with the names of the module's functions and of the tools that built it.
+const HINT_SECTIONS: [&str; 2] = ["name", "producers"];
+
+fn mentions_messagepack(bytes: &[u8]) -> bool {
+    let lowercase = bytes.to_ascii_lowercase();
+    MESSAGEPACK_HINTS.iter().any(|hint| {
+        lowercase
+            .windows(hint.len())
+            .any(|window| window == hint.as_bytes())
+    })
+}
+
+impl AutoCodec {
+    /// Look for hints of the encoding in a binary or text module. Modules importing a provider, such as Javy's,
+    /// read JSON. Others read MessagePack when their imports or their name or producers sections mention a
+    /// MessagePack library. Data isn't looked at, so a string mentioning MessagePack doesn't count.
+    pub fn for_module(bytes: &[u8]) -> Self {
+        let Ok(bytes) = wat::parse_bytes(bytes) else {
+            return Self::default();
+        };
+        let Ok(info) = ModuleInfo::parse(&bytes) else {
+            return Self::default();
+        };
+        if info.imports.values().any(|module| module.provider) {
+            return Self::default();
+        }
+        let imports_hint = info.imports.iter().any(|(module, imported)| {
+            mentions_messagepack(module.as_bytes())
+                || imported
+                    .imports
+                    .iter()
+                    .any(|import| mentions_messagepack(import.name.as_bytes()))
+        });
+        let sections_hint = Parser::new(0)
+            .parse_all(&bytes)
+            .any(|payload| match payload {
+                Ok(Payload::CustomSection(reader)) => {
+                    HINT_SECTIONS.contains(&reader.name()) && mentions_messagepack(reader.data())
+                }
+                _ => false,
+            });
+
+        Self {
+            messagepack: imports_hint || sections_hint,
+        }
+    }
+
+    fn preferred(&self) -> &'static dyn Codec {
+        if self.messagepack {
+            &MessagePackCodec
+        } else {
+            &JsonCodec
+        }
+    }
+}
+
+impl Codec for AutoCodec {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn encode_input(&self, input: Vec<u8>) -> Result<EncodedInput> {
+        self.preferred().encode_input(input)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+        self.detect(bytes).unwrap_or(self.preferred()).decode(bytes)
+    }
+
+    /// Valid JSON is detected as JSON, then valid MessagePack as MessagePack. Anything else isn't detected and is
+    /// decoded with the codec the module hints at.
+    fn detect(&self, bytes: &[u8]) -> Option<&'static dyn Codec> {
+        if JsonCodec.decode(bytes).is_ok() {
+            Some(&JsonCodec)
+        } else if MessagePackCodec.decode(bytes).is_ok() {
+            Some(&MessagePackCodec)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_json_input(input: &[u8]) -> Result<serde_json::Value> {
+    serde_json::from_slice(input).map_err(|e| anyhow!("Invalid input JSON: {}", e))
+}
+
+fn decode_json(bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+    serde_json::from_slice(bytes).map_err(|error| {
+        // serde_json reports 1-based lines and columns, convert them to a byte offset.
+        let line_start: usize = bytes
+            .split_inclusive(|byte| *byte == b'\n')
+            .take(error.line().saturating_sub(1))
+            .map(<[u8]>::len)
+            .sum();
+        DecodeError {
+            message: error.to_string(),
+            offset: line_start + error.column().saturating_sub(1),
+        }
+    })
+}
+
+impl Codec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn encode_input(&self, input: Vec<u8>) -> Result<EncodedInput> {
+        let value = parse_json_input(&input)?;
+        let bytes =
+            serde_json::to_vec(&value).map_err(|e| anyhow!("Couldn't serialize JSON: {}", e))?;
+
+        Ok(EncodedInput {
+            bytes,
+            value: Some(value),
+        })
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+        decode_json(bytes)
+    }
+
+    /// JSON is measured minified, whitespace the Function writes doesn't count.
+    fn size(&self, bytes: &[u8]) -> usize {
+        decode_json(bytes)
+            .ok()
+            .and_then(|value| serde_json::to_vec(&value).ok())
+            .map_or(bytes.len(), |minified| minified.len())
+    }
+}
+
+impl Codec for RawCodec {
+    fn name(&self) -> &'static str {
+        "raw"
+    }
+
+    fn encode_input(&self, input: Vec<u8>) -> Result<EncodedInput> {
+        Ok(EncodedInput {
+            bytes: input,
+            value: None,
+        })
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+        decode_json(bytes)
+    }
+}
+
+impl Codec for MessagePackCodec {
+    fn name(&self) -> &'static str {
+        "messagepack"
+    }
+
+    fn encode_input(&self, input: Vec<u8>) -> Result<EncodedInput> {
+        let value = parse_json_input(&input)?;
+        let bytes = rmp_serde::to_vec(&value)
+            .map_err(|e| anyhow!("Couldn't convert JSON to MessagePack: {}", e))?;
+
+        Ok(EncodedInput {
+            bytes,
+            value: Some(value),
+        })
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+        let mut cursor = std::io::Cursor::new(bytes);
+        let decoded =
+            serde_json::Value::deserialize(&mut rmp_serde::Deserializer::new(&mut cursor));
+        let offset = cursor.position() as usize;
+        match decoded {
+            Ok(value) if offset == bytes.len() => Ok(value),
+            Ok(_) => Err(DecodeError {
+                message: format!(
+                    "{} trailing bytes after the MessagePack value",
+                    bytes.len() - offset
+                ),
+                offset,
+            }),
+            Err(error) => Err(DecodeError {
+                message: error.to_string(),
+                offset,
+            }),
+        }
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_codecs() -> Result<()> {
+        let input = b"{\n  \"a\": [1, \"b\"]\n}".to_vec();
+
+        let encoded = JsonCodec.encode_input(input.clone())?;
+        assert_eq!(encoded.bytes, br#"{"a":[1,"b"]}"#);
+        assert_eq!(encoded.value, Some(json!({"a": [1, "b"]})));
+        assert_eq!(JsonCodec.size(&input), 13);
+
+        let encoded = RawCodec.encode_input(input.clone())?;
+        assert_eq!(encoded.bytes, input);
+        assert_eq!(encoded.value, None);
+        assert_eq!(RawCodec.size(&input), input.len());
+
+        let encoded = MessagePackCodec.encode_input(input)?;
+        assert_eq!(encoded.bytes, [0x81, 0xa1, 0x61, 0x92, 0x01, 0xa1, 0x62]);
+        assert_eq!(
+            MessagePackCodec.decode(&encoded.bytes),
+            Ok(json!({"a": [1, "b"]}))
+        );
+
+        assert!(JsonCodec.encode_input(b"{".to_vec()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let error = JsonCodec.decode(b"{\n  \"a\": 1,\n}").unwrap_err();
+        assert_eq!(error.offset, 12);
+
+        let bytes = rmp_serde::to_vec(&json!({"a": [1, "b"]})).unwrap();
+        let error = MessagePackCodec
+            .decode(&bytes[..bytes.len() - 1])
+            .unwrap_err();
+        assert_eq!(error.offset, bytes.len() - 1);
+
+        let error = MessagePackCodec
+            .decode(&[bytes.as_slice(), &[0xc0]].concat())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "1 trailing bytes after the MessagePack value (at byte {})",
+                bytes.len()
+            )
+        );
+    }
+
+    #[test]
+    fn test_auto_codec() -> Result<()> {
+        let codec = AutoCodec::for_module(
+            br#"(module
+                (memory (export "memory") 1)
+                (func $rmp_serde::decode::from_slice)
+                (func (export "_start") (call $rmp_serde::decode::from_slice))
+            )"#,
+        );
+        assert!(codec.messagepack);
+        assert_eq!(
+            codec.encode_input(b"{\"a\": 1}".to_vec())?.bytes,
+            [0x81, 0xa1, 0x61, 0x01]
+        );
+
+        let detected = |bytes: &[u8]| codec.detect(bytes).map(|codec| codec.name());
+        assert_eq!(detected(br#"{"a":1}"#), Some("json"));
+        assert_eq!(detected(&[0x81, 0xa1, 0x61, 0x01]), Some("messagepack"));
+        assert_eq!(detected(&[0x81, 0xa1]), None);
+        assert_eq!(codec.decode(&[0x81, 0xa1]).unwrap_err().offset, 2);
+
+        let codec = AutoCodec::for_module(
+            br#"(module
+                (import "javy_quickjs_provider_v1" "memory" (memory 1))
+                (data (i32.const 0) "msgpack")
+                (func (export "_start"))
+            )"#,
+        );
+        assert!(!codec.messagepack);
+        assert_eq!(codec.detect(&[0x81, 0xa1]).map(|codec| codec.name()), None);
+        assert_eq!(codec.decode(&[0x81, 0xa1]).unwrap_err().offset, 0);
+
+        // Strings in data, such as an error message, aren't hints
+        let codec = AutoCodec::for_module(
+            br#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "expected JSON, not msgpack from rmp-serde")
+                (func (export "_start"))
+            )"#,
+        );
+        assert!(!codec.messagepack);
+        assert_eq!(detected(br#"{"a":1}"#), Some("json"));
+        assert_eq!(
+            codec
+                .detect(&[0x81, 0xa1, 0x61, 0x01])
+                .map(|codec| codec.name()),
+            Some("messagepack")
+        );
+        assert_eq!(codec.detect(&[0x81, 0xa1]).map(|codec| codec.name()), None);
+
+        Ok(())
+    }
+}
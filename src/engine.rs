@@ -1,17 +1,34 @@
 use anyhow::{anyhow, Result};
 use rust_embed::RustEmbed;
-use std::{collections::HashSet, io::Cursor, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+    time::Duration,
+};
 use wasi_common::{I32Exit, WasiCtx};
-use wasmtime::{AsContextMut, Config, Engine, Linker, Module, ResourceLimiter, Store};
+use wasmparser::{ExternalKind, Parser, Payload, TypeRef};
+use wasmtime::{
+    AsContextMut, Config, Engine, Instance, InstancePre, Linker, Memory, Module, ResourceLimiter,
+    Store, Trap, UpdateDeadline,
+};
 
 use crate::{
+    codec::{Codec, EncodedInput, JsonCodec},
+    epoch_ticker::EpochTicker,
+    error::Error,
     function_run_result::{
-        FunctionOutput::{self, InvalidJsonOutput, JsonOutput},
-        FunctionRunResult, InvalidOutput,
+        BudgetUsage, FunctionInput, FunctionOutcome, FunctionOutput, FunctionRunResult,
+        MemoryUsage, ResourceLimits, TrapKind, DEFAULT_LINEAR_MEMORY_LIMIT,
     },
+    limit_profile::LimitProfile,
     logs::LogStream,
+    module_cache::ModuleCache,
+    module_check::{self, DisallowedImport},
 };
 
+/// Profiling relies on process-wide state, only one profiled run may be in progress at a time.
 #[derive(Clone)]
 pub struct ProfileOpts {
     pub interval: u32,
@@ -22,76 +39,344 @@ pub struct ProfileOpts {
 #[folder = "providers/"]
 struct StandardProviders;
 
-fn import_modules<T>(
+/// Whether an imported module is satisfied by one of the providers embedded in the runner.
+pub fn is_standard_provider(module_name: &str) -> bool {
+    StandardProviders::get(&format!("{module_name}.wasm")).is_some()
+}
+
+/// Module names of the embedded providers, e.g. `javy_quickjs_provider_v1`.
+pub fn standard_provider_names() -> Vec<String> {
+    let mut names: Vec<String> = StandardProviders::iter()
+        .filter_map(|file_name| file_name.strip_suffix(".wasm").map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Create an engine configured like the one running Functions in production.
+pub fn new_engine() -> Result<Engine> {
+    Engine::new(
+        Config::new()
+            .wasm_multi_memory(true)
+            .wasm_threads(false)
+            .wasm_reference_types(false)
+            .consume_fuel(true)
+            .epoch_interruption(true),
+    )
+}
+
+fn compile_module(
+    engine: &Engine,
+    bytes: &[u8],
+    module_cache: Option<&ModuleCache>,
+) -> Result<Module> {
+    match module_cache {
+        Some(module_cache) => module_cache.load_or_compile(engine, bytes),
+        None => Module::new(engine, bytes),
+    }
+}
+
+/// Module names of the embedded Javy providers start with this, followed by their version.
+const JAVY_PROVIDER_PREFIX: &str = "javy_quickjs_provider_v";
+
+/// Prepare an instance of each embedded provider imported by the module.
+fn import_modules(
+    name: &str,
     module: &Module,
     engine: &Engine,
-    linker: &mut Linker<T>,
-    mut store: &mut Store<T>,
-) {
-    let imported_modules: HashSet<String> =
-        module.imports().map(|i| i.module().to_string()).collect();
-    imported_modules.iter().for_each(|module_name| {
-        let imported_module_bytes = StandardProviders::get(&format!("{module_name}.wasm"));
-
-        if let Some(bytes) = imported_module_bytes {
-            let imported_module = Module::from_binary(engine, &bytes.data)
-                .unwrap_or_else(|_| panic!("Failed to load module {module_name}"));
-
-            let imported_module_instance = linker
-                .instantiate(&mut store, &imported_module)
-                .expect("Failed to instantiate imported instance");
-            linker
-                .instance(&mut store, module_name, imported_module_instance)
-                .expect("Failed to import module");
-        }
-    });
+    linker: &Linker<FunctionContext>,
+    module_cache: Option<&ModuleCache>,
+) -> Result<Vec<(String, InstancePre<FunctionContext>)>, Error> {
+    let mut imported_modules: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for import in module.imports() {
+        imported_modules
+            .entry(import.module())
+            .or_default()
+            .push(import.name());
+    }
+
+    let mut providers = vec![];
+    for (module_name, imports) in imported_modules {
+        let Some(bytes) = StandardProviders::get(&format!("{module_name}.wasm")) else {
+            if module_name.starts_with(JAVY_PROVIDER_PREFIX) {
+                return Err(Error::Link {
+                    name: name.to_string(),
+                    message: format!(
+                        "unknown provider {:?} imported for {}, available providers are: {}",
+                        module_name,
+                        imports.join(", "),
+                        standard_provider_names().join(", ")
+                    ),
+                });
+            }
+            continue;
+        };
+
+        let provider =
+            compile_module(engine, &bytes.data, module_cache).map_err(|e| Error::Compile {
+                name: module_name.to_string(),
+                message: e.to_string(),
+            })?;
+        let provider_pre = linker.instantiate_pre(&provider).map_err(|e| Error::Link {
+            name: name.to_string(),
+            message: format!("couldn't link provider {:?}: {}", module_name, e),
+        })?;
+
+        providers.push((module_name.to_string(), provider_pre));
+    }
+
+    Ok(providers)
+}
+
+/// Where to load the Function's module from.
+pub enum FunctionSource {
+    Path(PathBuf),
+    /// Wasm or wat bytes, the name is used in the run result.
+    Bytes {
+        name: String,
+        bytes: Vec<u8>,
+    },
+    /// A module compiled with an engine created by [`new_engine`]. Its size isn't known and is reported as 0.
+    Module {
+        name: String,
+        module: Module,
+    },
+}
+
+impl Default for FunctionSource {
+    fn default() -> Self {
+        Self::Path(PathBuf::default())
+    }
+}
+
+impl From<PathBuf> for FunctionSource {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&Path> for FunctionSource {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.to_path_buf())
+    }
 }
 
 #[derive(Default)]
 pub struct FunctionRunParams<'a> {
-    pub function_path: PathBuf,
+    pub function: FunctionSource,
     pub input: Vec<u8>,
     pub export: &'a str,
+    /// Load compiled modules from this cache, compiling and storing them on a miss.
+    pub module_cache: Option<&'a ModuleCache>,
+    /// How the Function is run, the same options as [`FunctionRunner::run_with_options`].
+    pub options: RunOptions<'a>,
+}
+
+/// Options for a single run of a [`FunctionRunner`].
+#[derive(Clone)]
+pub struct RunOptions<'a> {
     pub profile_opts: Option<&'a ProfileOpts>,
     pub scale_factor: f64,
+    /// Stop the Function once it has used the scaled instruction limit, like production does.
+    pub enforce_instructions_limit: bool,
+    /// Interrupt the Function once it has been running for this long. Not enforced while profiling.
+    pub timeout: Option<Duration>,
+    /// Deny growing any linear memory beyond this many bytes, the production limit by default. `None` doesn't limit
+    /// memory.
+    pub memory_limit: Option<u64>,
+    /// The limits the run is judged against, the production defaults when omitted.
+    pub limit_profile: Option<&'a LimitProfile>,
+    /// How the input is encoded, to show and measure it in the result. JSON when omitted. The codec detected by
+    /// codecs such as [`crate::codec::AutoCodec`] is reported in the result.
+    pub input_codec: Option<&'a dyn Codec>,
+    /// Encode the input with the input codec before running, e.g. convert a JSON input file to MessagePack.
+    /// Input it can't encode fails with [`Error::InvalidInput`].
+    pub encode_input: bool,
+    /// How to decode what the Function writes to stdout. JSON when omitted.
+    pub output_codec: Option<&'a dyn Codec>,
+}
+
+impl Default for RunOptions<'_> {
+    fn default() -> Self {
+        Self {
+            profile_opts: None,
+            scale_factor: 1.0,
+            enforce_instructions_limit: false,
+            timeout: None,
+            memory_limit: Some(DEFAULT_LINEAR_MEMORY_LIMIT),
+            limit_profile: None,
+            input_codec: None,
+            encode_input: false,
+            output_codec: None,
+        }
+    }
 }
 
 const STARTING_FUEL: u64 = u64::MAX;
+/// Name of the Function's own instance in the memory usages, providers are named after their module.
+const FUNCTION_INSTANCE: &str = "Function";
 const MAXIMUM_MEMORIES: usize = 2; // 1 for the module, 1 for Javy's provider
 
 struct FunctionContext {
     wasi: WasiCtx,
     limiter: MemoryLimiter,
+    /// Memories exported by each instance, with the instance's name and their index among the memories it defines.
+    exported_memories: Vec<(String, u32, Memory)>,
 }
 
 impl FunctionContext {
-    fn new(wasi: WasiCtx) -> Self {
+    fn new(wasi: WasiCtx, memory_limit: Option<u64>) -> Self {
         Self {
             wasi,
-            limiter: Default::default(),
+            limiter: MemoryLimiter::new(memory_limit),
+            exported_memories: vec![],
         }
     }
+}
+
+/// Usage of each memory created in the store, read once the Function has run. Memories never shrink, so the size
+/// of an exported memory after the run is its peak. The sizes of memories that aren't exported can't be read.
+fn memory_usages(store: &Store<FunctionContext>) -> Vec<MemoryUsage> {
+    let context = store.data();
+    context
+        .limiter
+        .memories
+        .iter()
+        .map(|memory| MemoryUsage {
+            instance: memory.instance.clone(),
+            index: memory.index,
+            initial: memory.initial as u64,
+            peak: context
+                .exported_memories
+                .iter()
+                .find(|(instance, index, _)| *instance == memory.instance && *index == memory.index)
+                .map(|(_, _, handle)| handle.data_size(store) as u64),
+        })
+        .collect()
+}
+
+/// Memories a module exports, along with their index among the memories it defines. Imported memories exported
+/// again are left out, they are tracked in the instance defining them.
+fn exported_memories(bytes: &[u8]) -> Vec<(u32, String)> {
+    let Ok(bytes) = wat::parse_bytes(bytes) else {
+        return vec![];
+    };
 
-    fn max_memory_bytes(&self) -> usize {
-        self.limiter.max_memory_bytes
+    let mut imported = 0;
+    let mut exported = vec![];
+    for payload in Parser::new(0).parse_all(&bytes) {
+        match payload {
+            Ok(Payload::ImportSection(reader)) => {
+                imported += reader
+                    .into_iter()
+                    .filter(|import| {
+                        import
+                            .as_ref()
+                            .is_ok_and(|import| matches!(import.ty, TypeRef::Memory(_)))
+                    })
+                    .count() as u32;
+            }
+            Ok(Payload::ExportSection(reader)) => {
+                for export in reader.into_iter().flatten() {
+                    if export.kind == ExternalKind::Memory && export.index >= imported {
+                        exported.push((export.index - imported, export.name.to_string()));
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(_) => return vec![],
+        }
     }
+
+    exported
 }
 
+struct TrackedMemory {
+    instance: String,
+    index: u32,
+    initial: usize,
+}
+
+/// Denies growing memories beyond the limit and keeps track of the memories created and grown in the store.
+///
+/// `ResourceLimiter` isn't told which memory is growing, so grows are only counted for the whole store.
 #[derive(Default)]
 pub struct MemoryLimiter {
-    max_memory_bytes: usize,
+    limit: Option<usize>,
+    memories: Vec<TrackedMemory>,
+    instantiating: Option<String>,
+    grow_calls: u64,
+    /// Largest size of any memory, and the one before the last grow in case it fails.
+    peak: usize,
+    peak_before_grow: usize,
+    limit_exceeded: bool,
+}
+
+impl MemoryLimiter {
+    fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit: limit.map(|limit| usize::try_from(limit).unwrap_or(usize::MAX)),
+            ..Default::default()
+        }
+    }
+
+    /// Memories are only created while instantiating, growth from zero bytes in that window is a new memory
+    /// owned by the instance being instantiated.
+    fn set_instantiating(&mut self, instance: Option<&str>) {
+        self.instantiating = instance.map(str::to_string);
+    }
+
+    /// Whether a memory tried to grow beyond the limit and was denied.
+    fn limit_exceeded(&self) -> bool {
+        self.limit_exceeded
+    }
+
+    fn track_new_memory(&mut self, instance: String, initial: usize) {
+        let index = self
+            .memories
+            .iter()
+            .filter(|memory| memory.instance == instance)
+            .count() as u32;
+        self.memories.push(TrackedMemory {
+            instance,
+            index,
+            initial,
+        });
+    }
 }
 
 impl ResourceLimiter for MemoryLimiter {
     /// See [`wasmtime::ResourceLimiter::memory_growing`].
     fn memory_growing(
         &mut self,
-        _current: usize,
+        current: usize,
         desired: usize,
         _maximum: Option<usize>,
     ) -> anyhow::Result<bool> {
-        self.max_memory_bytes = std::cmp::max(self.max_memory_bytes, desired);
-        Ok(true)
+        let allowed = !self.limit.is_some_and(|limit| desired > limit);
+        self.limit_exceeded |= !allowed;
+
+        match self.instantiating.clone() {
+            Some(instance) if current == 0 => {
+                if allowed {
+                    self.track_new_memory(instance, desired);
+                }
+            }
+            _ => self.grow_calls += 1,
+        }
+        self.peak_before_grow = self.peak;
+        if allowed {
+            self.peak = self.peak.max(desired);
+        }
+
+        Ok(allowed)
+    }
+
+    /// See [`wasmtime::ResourceLimiter::memory_grow_failed`].
+    fn memory_grow_failed(&mut self, _error: anyhow::Error) -> anyhow::Result<()> {
+        self.peak = self.peak_before_grow;
+
+        Ok(())
     }
 
     /// See [`wasmtime::ResourceLimiter::table_growing`].
@@ -109,134 +394,567 @@ impl ResourceLimiter for MemoryLimiter {
     }
 }
 
-pub fn run(params: FunctionRunParams) -> Result<FunctionRunResult> {
-    let FunctionRunParams {
-        function_path,
-        input,
-        export,
-        profile_opts,
-        scale_factor,
-    } = params;
+/// Where an import of the Function is taken from when instantiating it.
+enum ImportSource {
+    /// An export of the provider at this index of the runner's providers.
+    Provider { provider: usize, name: String },
+    /// A definition of the linker, such as a WASI function.
+    Linker { module: String, name: String },
+}
 
-    let engine = Engine::new(
-        Config::new()
-            .wasm_multi_memory(true)
-            .wasm_threads(false)
-            .wasm_reference_types(false)
-            .consume_fuel(true)
-            .epoch_interruption(true),
-    )?;
-    let module = Module::from_file(&engine, &function_path)
-        .map_err(|e| anyhow!("Couldn't load the Function {:?}: {}", &function_path, e))?;
+/// A Function compiled once, along with the providers it imports, which can be run many times.
+///
+/// Runs only share the compiled code, each one gets its own store, so a runner can be used from several
+/// threads at once.
+pub struct FunctionRunner {
+    engine: Engine,
+    module: Module,
+    name: String,
+    size: u64,
+    /// The wasm or wat the module was compiled from, unknown for precompiled modules.
+    bytes: Option<Vec<u8>>,
+    linker: Linker<FunctionContext>,
+    providers: Vec<(String, InstancePre<FunctionContext>)>,
+    /// Only available when the module doesn't import any provider, as those are instantiated in each store.
+    instance_pre: Option<InstancePre<FunctionContext>>,
+    /// Where each import of the module comes from, resolved once so that modules importing providers are
+    /// instantiated from the provider instances of each store without linking them again.
+    imports: Vec<ImportSource>,
+    /// The imports disallowed by each limit profile the runner was run with, see [`Self::check_imports`].
+    checked_imports: Mutex<Vec<(LimitProfile, Vec<DisallowedImport>)>>,
+    /// Memories exported by the Function and by each provider, see [`exported_memories`].
+    memory_exports: BTreeMap<String, Vec<(u32, String)>>,
+    ticker: OnceLock<EpochTicker>,
+}
 
-    let input_stream = wasi_common::pipe::ReadPipe::new(Cursor::new(input.clone()));
-    let output_stream = wasi_common::pipe::WritePipe::new_in_memory();
-    let error_stream = wasi_common::pipe::WritePipe::new(LogStream::default());
+impl FunctionRunner {
+    pub fn from_source(
+        source: FunctionSource,
+        module_cache: Option<&ModuleCache>,
+    ) -> Result<Self, Error> {
+        match source {
+            FunctionSource::Path(path) => Self::from_path(path, module_cache),
+            FunctionSource::Bytes { name, bytes } => Self::from_bytes(name, &bytes, module_cache),
+            FunctionSource::Module { name, module } => Self::from_module(name, module),
+        }
+    }
+
+    pub fn from_path(
+        function_path: impl AsRef<Path>,
+        module_cache: Option<&ModuleCache>,
+    ) -> Result<Self, Error> {
+        let function_path = function_path.as_ref();
+        let engine = new_engine().map_err(|e| Error::Internal(e.to_string()))?;
+        let bytes = std::fs::read(function_path).map_err(|e| Error::Load {
+            name: function_path.display().to_string(),
+            message: format!(
+                "failed to read input file: {}: {}",
+                function_path.display(),
+                e
+            ),
+        })?;
+        let module = compile_module(&engine, &bytes, module_cache).map_err(|e| Error::Compile {
+            name: function_path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        let name = function_path
+            .file_name()
+            .unwrap_or(function_path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        let size = bytes.len() as u64 / 1024;
+
+        Self::new(engine, module, name, size, Some(bytes), module_cache)
+    }
 
-    let memory_usage: u64;
-    let instructions: u64;
-    let mut error_logs: String = String::new();
-    let profile_data: Option<String>;
+    pub fn from_bytes(
+        name: impl Into<String>,
+        bytes: &[u8],
+        module_cache: Option<&ModuleCache>,
+    ) -> Result<Self, Error> {
+        let name = name.into();
+        let engine = new_engine().map_err(|e| Error::Internal(e.to_string()))?;
+        let module = compile_module(&engine, bytes, module_cache).map_err(|e| Error::Compile {
+            name: name.clone(),
+            message: e.to_string(),
+        })?;
+        let size = bytes.len() as u64 / 1024;
 
-    {
+        Self::new(
+            engine,
+            module,
+            name,
+            size,
+            Some(bytes.to_vec()),
+            module_cache,
+        )
+    }
+
+    /// Use a module compiled with an engine created by [`new_engine`].
+    pub fn from_module(name: impl Into<String>, module: Module) -> Result<Self, Error> {
+        let engine = module.engine().clone();
+
+        Self::new(engine, module, name.into(), 0, None, None)
+    }
+
+    fn new(
+        engine: Engine,
+        module: Module,
+        name: String,
+        size: u64,
+        bytes: Option<Vec<u8>>,
+        module_cache: Option<&ModuleCache>,
+    ) -> Result<Self, Error> {
         let mut linker = Linker::new(&engine);
-        wasi_common::sync::add_to_linker(&mut linker, |ctx: &mut FunctionContext| &mut ctx.wasi)?;
-        let wasi = deterministic_wasi_ctx::build_wasi_ctx();
-        wasi.set_stdin(Box::new(input_stream));
-        wasi.set_stdout(Box::new(output_stream.clone()));
-        wasi.set_stderr(Box::new(error_stream.clone()));
-        let function_context = FunctionContext::new(wasi);
-        let mut store = Store::new(&engine, function_context);
-        store.limiter(|s| &mut s.limiter);
-        store.set_fuel(STARTING_FUEL)?;
-        store.set_epoch_deadline(1);
-
-        import_modules(&module, &engine, &mut linker, &mut store);
-
-        linker.module(&mut store, "Function", &module)?;
-        let instance = linker.instantiate(&mut store, &module)?;
-
-        let func = instance.get_typed_func::<(), ()>(store.as_context_mut(), export)?;
-
-        let module_result;
-        (module_result, profile_data) = if let Some(profile_opts) = profile_opts {
-            let (result, profile_data) = wasmprof::ProfilerBuilder::new(&mut store)
-                .frequency(profile_opts.interval)
-                .weight_unit(wasmprof::WeightUnit::Fuel)
-                .profile(|store| func.call(store.as_context_mut(), ()));
-
-            (
-                result,
-                Some(profile_data.into_collapsed_stacks().to_string()),
-            )
+        wasi_common::sync::add_to_linker(&mut linker, |ctx: &mut FunctionContext| &mut ctx.wasi)
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        let providers = import_modules(&name, &module, &engine, &linker, module_cache)?;
+        let mut memory_exports: BTreeMap<String, Vec<(u32, String)>> = providers
+            .iter()
+            .filter_map(|(module_name, _)| {
+                StandardProviders::get(&format!("{module_name}.wasm"))
+                    .map(|provider| (module_name.clone(), exported_memories(&provider.data)))
+            })
+            .collect();
+        if let Some(bytes) = &bytes {
+            memory_exports.insert(FUNCTION_INSTANCE.to_string(), exported_memories(bytes));
+        }
+        // Modules importing something the linker doesn't define are reported when they're run, once the limit
+        // profile is known.
+        let instance_pre = if providers.is_empty() {
+            linker.instantiate_pre(&module).ok()
+        } else {
+            None
+        };
+        let imports = module
+            .imports()
+            .map(|import| {
+                match providers
+                    .iter()
+                    .position(|(module_name, _)| module_name == import.module())
+                {
+                    Some(provider) => ImportSource::Provider {
+                        provider,
+                        name: import.name().to_string(),
+                    },
+                    None => ImportSource::Linker {
+                        module: import.module().to_string(),
+                        name: import.name().to_string(),
+                    },
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            engine,
+            module,
+            name,
+            size,
+            bytes,
+            linker,
+            providers,
+            instance_pre,
+            imports,
+            checked_imports: Mutex::default(),
+            memory_exports,
+            ticker: OnceLock::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Run the export with the default [`RunOptions`].
+    pub fn run(&self, input: Vec<u8>, export: &str) -> Result<FunctionRunResult, Error> {
+        self.run_with_options(input, export, &RunOptions::default())
+    }
+
+    pub fn run_with_options(
+        &self,
+        input: Vec<u8>,
+        export: &str,
+        options: &RunOptions,
+    ) -> Result<FunctionRunResult, Error> {
+        let RunOptions {
+            profile_opts,
+            scale_factor,
+            enforce_instructions_limit,
+            timeout,
+            memory_limit,
+            limit_profile,
+            input_codec,
+            encode_input,
+            output_codec,
+        } = *options;
+
+        let input = if encode_input {
+            self::encode_input(input, input_codec)?.bytes
+        } else {
+            input
+        };
+
+        let limit_profile = limit_profile.cloned().unwrap_or_default();
+        let disallowed_imports = self.check_imports(&limit_profile)?;
+        let limits = limit_profile.scaled(scale_factor);
+        let starting_fuel = if enforce_instructions_limit {
+            limits.instructions
         } else {
-            (func.call(store.as_context_mut(), ()), None)
+            STARTING_FUEL
         };
 
-        // modules may exit with a specific exit code, an exit code of 0 is considered success but is reported as
-        // a GuestFault by wasmtime, so we need to map it to a success result. Any other exit code is considered
-        // a failure.
-        let module_result =
-            module_result.or_else(|error| match error.downcast_ref::<wasi_common::I32Exit>() {
+        let input_stream = wasi_common::pipe::ReadPipe::new(Cursor::new(input.clone()));
+        let output_stream = wasi_common::pipe::WritePipe::new_in_memory();
+        let error_stream = wasi_common::pipe::WritePipe::new(LogStream::default());
+
+        let memory_usage: u64;
+        let memories: Vec<MemoryUsage>;
+        let memory_grow_calls: u64;
+        let instructions: u64;
+        let outcome: FunctionOutcome;
+        let profile_data: Option<String>;
+
+        {
+            let wasi = deterministic_wasi_ctx::build_wasi_ctx();
+            wasi.set_stdin(Box::new(input_stream));
+            wasi.set_stdout(Box::new(output_stream.clone()));
+            wasi.set_stderr(Box::new(error_stream.clone()));
+            let function_context = FunctionContext::new(wasi, memory_limit);
+            let mut store = Store::new(&self.engine, function_context);
+            store.limiter(|s| &mut s.limiter);
+            store
+                .set_fuel(starting_fuel)
+                .map_err(|e| Error::Internal(e.to_string()))?;
+            match timeout {
+                Some(timeout) if profile_opts.is_none() => {
+                    self.ticker.get_or_init(|| EpochTicker::start(&self.engine));
+                    store.set_epoch_deadline(EpochTicker::deadline_for(timeout));
+                }
+                // the ticker may be running for other runs, keep going when it ticks
+                _ => {
+                    store.set_epoch_deadline(1);
+                    store.epoch_deadline_callback(|_| Ok(UpdateDeadline::Continue(1)));
+                }
+            }
+
+            let instance = self.instantiate(&mut store);
+
+            let module_result;
+            (module_result, profile_data) = match instance {
+                Ok(instance) => {
+                    let func = instance
+                        .get_typed_func::<(), ()>(store.as_context_mut(), export)
+                        .map_err(|e| Error::Export {
+                            name: self.name.clone(),
+                            export: export.to_string(),
+                            message: format!("{}, exports are called as () -> ()", e),
+                        })?;
+
+                    if let Some(profile_opts) = profile_opts {
+                        let (result, profile_data) = wasmprof::ProfilerBuilder::new(&mut store)
+                            .frequency(profile_opts.interval)
+                            .weight_unit(wasmprof::WeightUnit::Fuel)
+                            .profile(|store| func.call(store.as_context_mut(), ()));
+
+                        (
+                            result,
+                            Some(profile_data.into_collapsed_stacks().to_string()),
+                        )
+                    } else {
+                        (func.call(store.as_context_mut(), ()), None)
+                    }
+                }
+                // a memory declaring more initial pages than the limit fails instantiation
+                Err(error) if store.data().limiter.limit_exceeded() => (Err(error), None),
+                Err(error) => {
+                    return Err(Error::Link {
+                        name: self.name.clone(),
+                        message: error.to_string(),
+                    })
+                }
+            };
+
+            // modules may exit with a specific exit code, an exit code of 0 is considered success but is reported as
+            // a GuestFault by wasmtime, so we need to map it to a success result. Any other exit code is considered
+            // a failure.
+            let module_result = module_result.or_else(|error| match error.downcast_ref() {
                 Some(I32Exit(0)) => Ok(()),
-                Some(I32Exit(code)) => Err(anyhow!("module exited with code: {}", code)),
-                None => Err(error),
+                _ => Err(error),
             });
 
-        memory_usage = store.data().max_memory_bytes() as u64 / 1024;
-        instructions = STARTING_FUEL.saturating_sub(store.get_fuel().unwrap_or_default());
+            memories = memory_usages(&store);
+            memory_grow_calls = store.data().limiter.grow_calls;
+            memory_usage = store.data().limiter.peak as u64 / 1024;
+            instructions = starting_fuel.saturating_sub(store.get_fuel().unwrap_or_default());
 
-        match module_result {
-            Ok(_) => {}
-            Err(e) => {
-                error_logs = e.to_string();
+            outcome = match module_result {
+                Ok(_) => FunctionOutcome::Success,
+                Err(e) if matches!(e.downcast_ref::<Trap>(), Some(Trap::OutOfFuel)) => {
+                    FunctionOutcome::InstructionLimitExceeded {
+                        limit: starting_fuel,
+                    }
+                }
+                Err(e) if matches!(e.downcast_ref::<Trap>(), Some(Trap::Interrupt)) => {
+                    FunctionOutcome::TimedOut {
+                        timeout_ms: timeout.unwrap_or_default().as_millis() as u64,
+                    }
+                }
+                Err(_) if store.data().limiter.limit_exceeded() => {
+                    FunctionOutcome::MemoryLimitExceeded {
+                        limit: memory_limit.unwrap_or_default(),
+                    }
+                }
+                Err(e) => match e.downcast_ref() {
+                    Some(I32Exit(code)) => FunctionOutcome::Exited { code: *code },
+                    None => FunctionOutcome::Trapped {
+                        kind: e
+                            .downcast_ref::<Trap>()
+                            .map_or(TrapKind::Host, |trap| TrapKind::from(*trap)),
+                        message: e.to_string(),
+                    },
+                },
+            };
+        };
+
+        let logs = error_stream.try_into_inner().map_err(|_| {
+            Error::Internal(
+                "Couldn't read the Function's logs, the log stream is still in use".to_string(),
+            )
+        })?;
+
+        let raw_output = output_stream
+            .try_into_inner()
+            .map_err(|_| {
+                Error::Internal(
+                    "Couldn't read the Function's output, the output stream is still in use"
+                        .to_string(),
+                )
+            })?
+            .into_inner();
+
+        let input_codec = input_codec.unwrap_or(&JsonCodec);
+        let input_codec = input_codec.detect(&input).unwrap_or(input_codec);
+        let output_codec = output_codec.unwrap_or(&JsonCodec);
+        let output_codec = output_codec.detect(&raw_output).unwrap_or(output_codec);
+
+        let mut function_run_result = FunctionRunResult {
+            name: self.name.clone(),
+            size: self.size,
+            memory_usage,
+            memories,
+            memory_grow_calls,
+            instructions,
+            logs: logs.to_string(),
+            input: FunctionInput::decode(&input, input_codec),
+            input_codec: input_codec.name().to_string(),
+            raw_input_size: Some(input_codec.size(&input)),
+            output: FunctionOutput::decode(&raw_output, output_codec),
+            output_codec: output_codec.name().to_string(),
+            raw_output_size: Some(output_codec.size(&raw_output)),
+            outcome,
+            scale_factor,
+            limits: ResourceLimits::default(),
+            budget_used: BudgetUsage::default(),
+            violations: vec![],
+            disallowed_imports: disallowed_imports.iter().map(ToString::to_string).collect(),
+            profile: profile_data,
+        };
+        function_run_result.apply_limits(limits);
+
+        Ok(function_run_result)
+    }
+
+    /// The imports the limit profile doesn't allow. Fails before linking when the profile enforces its allowed
+    /// imports, or when the runner can't link them anyway.
+    fn check_imports(&self, limit_profile: &LimitProfile) -> Result<Vec<DisallowedImport>, Error> {
+        let disallowed_imports = self.disallowed_imports(limit_profile)?;
+        if !limit_profile.enforce_imports
+            && disallowed_imports.iter().all(DisallowedImport::is_linkable)
+        {
+            return Ok(disallowed_imports);
+        }
+
+        Err(Error::Link {
+            name: self.name.clone(),
+            message: format!(
+                "it imports what the limit profile doesn't allow:\n{}",
+                disallowed_imports
+                    .iter()
+                    .map(|import| format!("  {import}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        })
+    }
+
+    /// The imports the limit profile doesn't allow, only looked for the first time the runner is run with it.
+    fn disallowed_imports(
+        &self,
+        limit_profile: &LimitProfile,
+    ) -> Result<Vec<DisallowedImport>, Error> {
+        let mut checked_imports = self
+            .checked_imports
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, disallowed_imports)) = checked_imports
+            .iter()
+            .find(|(checked_profile, _)| checked_profile == limit_profile)
+        {
+            return Ok(disallowed_imports.clone());
+        }
+
+        let disallowed_imports = if self
+            .module
+            .imports()
+            .all(|import| limit_profile.allows_import(import.module(), import.name()))
+        {
+            vec![]
+        } else {
+            self.find_disallowed_imports(limit_profile)?
+        };
+        checked_imports.push((limit_profile.clone(), disallowed_imports.clone()));
+
+        Ok(disallowed_imports)
+    }
+
+    /// Look for the imports the limit profile doesn't allow, along with their callers unless the module is
+    /// precompiled.
+    fn find_disallowed_imports(
+        &self,
+        limit_profile: &LimitProfile,
+    ) -> Result<Vec<DisallowedImport>, Error> {
+        match &self.bytes {
+            Some(bytes) => wat::parse_bytes(bytes)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| module_check::disallowed_imports(&bytes, limit_profile))
+                .map_err(|e| Error::Compile {
+                    name: self.name.clone(),
+                    message: e.to_string(),
+                }),
+            None => Ok(self
+                .module
+                .imports()
+                .enumerate()
+                .filter(|(_, import)| !limit_profile.allows_import(import.module(), import.name()))
+                .map(|(index, import)| DisallowedImport {
+                    module: import.module().to_string(),
+                    name: import.name().to_string(),
+                    index: index as u32,
+                    callers: vec![],
+                })
+                .collect()),
+        }
+    }
+
+    fn instantiate(&self, store: &mut Store<FunctionContext>) -> Result<Instance> {
+        let instance = match &self.instance_pre {
+            Some(instance_pre) => {
+                store
+                    .data_mut()
+                    .limiter
+                    .set_instantiating(Some(FUNCTION_INSTANCE));
+                instance_pre.instantiate(&mut *store)
             }
+            None => self.instantiate_with_providers(store),
+        };
+        store.data_mut().limiter.set_instantiating(None);
+        if let Ok(instance) = &instance {
+            self.track_exported_memories(store, FUNCTION_INSTANCE, instance);
         }
-    };
 
-    let mut logs = error_stream
-        .try_into_inner()
-        .expect("Log stream reference still exists");
-
-    logs.append(error_logs.as_bytes());
-
-    let raw_output = output_stream
-        .try_into_inner()
-        .expect("Output stream reference still exists")
-        .into_inner();
-
-    let output: FunctionOutput = match serde_json::from_slice(&raw_output) {
-        Ok(json_output) => JsonOutput(json_output),
-        Err(error) => InvalidJsonOutput(InvalidOutput {
-            stdout: std::str::from_utf8(&raw_output)
-                .map_err(|e| anyhow!("Couldn't print Function Output: {}", e))
-                .unwrap()
-                .to_owned(),
-            error: error.to_string(),
-        }),
-    };
+        instance
+    }
 
-    let name = function_path.file_name().unwrap().to_str().unwrap();
-    let size = function_path.metadata()?.len() / 1024;
+    /// Instantiate the providers in the store, then the module with its imports taken from them.
+    ///
+    /// The providers' instances, including the memories they export, belong to the store, so unlike a module
+    /// without providers this one can't be pre-instantiated. Its imports are resolved when the runner is created
+    /// and only looked up in the store here.
+    fn instantiate_with_providers(&self, store: &mut Store<FunctionContext>) -> Result<Instance> {
+        let mut provider_instances = Vec::with_capacity(self.providers.len());
+        for (module_name, provider_pre) in &self.providers {
+            store
+                .data_mut()
+                .limiter
+                .set_instantiating(Some(module_name));
+            let provider_instance = provider_pre
+                .instantiate(&mut *store)
+                .map_err(|e| anyhow!("Couldn't instantiate provider {:?}: {}", module_name, e))?;
+            self.track_exported_memories(store, module_name, &provider_instance);
+            provider_instances.push(provider_instance);
+        }
 
-    let parsed_input =
-        String::from_utf8(input).map_err(|e| anyhow!("Couldn't parse input: {}", e))?;
+        let imports = self
+            .imports
+            .iter()
+            .map(|import| match import {
+                ImportSource::Provider { provider, name } => provider_instances[*provider]
+                    .get_export(&mut *store, name)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "unknown import: provider {:?} doesn't export {:?}",
+                            self.providers[*provider].0,
+                            name
+                        )
+                    }),
+                ImportSource::Linker { module, name } => {
+                    self.linker.get(&mut *store, module, name).ok_or_else(|| {
+                        anyhow!("unknown import: `{module}::{name}` has not been defined")
+                    })
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-    let function_run_input = serde_json::from_str(&parsed_input)?;
+        store
+            .data_mut()
+            .limiter
+            .set_instantiating(Some(FUNCTION_INSTANCE));
+        Instance::new(&mut *store, &self.module, &imports)
+    }
 
-    let function_run_result = FunctionRunResult {
-        name: name.to_string(),
-        size,
-        memory_usage,
-        instructions,
-        logs: logs.to_string(),
-        input: function_run_input,
-        output,
-        profile: profile_data,
-        scale_factor,
-    };
+    /// Keep the memories the instance exports, to read their sizes once the Function has run.
+    fn track_exported_memories(
+        &self,
+        store: &mut Store<FunctionContext>,
+        name: &str,
+        instance: &Instance,
+    ) {
+        for (index, export) in self.memory_exports.get(name).into_iter().flatten() {
+            if let Some(memory) = instance.get_memory(&mut *store, export) {
+                store
+                    .data_mut()
+                    .exported_memories
+                    .push((name.to_string(), *index, memory));
+            }
+        }
+    }
+}
+
+pub fn run(params: FunctionRunParams) -> Result<FunctionRunResult, Error> {
+    let FunctionRunParams {
+        function,
+        input,
+        export,
+        module_cache,
+        options,
+    } = params;
 
-    Ok(function_run_result)
+    FunctionRunner::from_source(function, module_cache)?.run_with_options(input, export, &options)
+}
+
+/// Encode an input file with the codec, JSON when omitted.
+pub fn encode_input(input: Vec<u8>, codec: Option<&dyn Codec>) -> Result<EncodedInput, Error> {
+    codec
+        .unwrap_or(&JsonCodec)
+        .encode_input(input)
+        .map_err(|e| Error::InvalidInput {
+            message: e.to_string(),
+        })
 }
 
 #[cfg(test)]
@@ -245,6 +963,7 @@ mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::{codec::MessagePackCodec, function_run_result::BinaryInput};
     use std::path::Path;
 
     const DEFAULT_EXPORT: &str = "_start";
@@ -253,7 +972,7 @@ mod tests {
     fn test_js_function() {
         let input = include_bytes!("../tests/fixtures/input/js_function_input.json").to_vec();
         let function_run_result = run(FunctionRunParams {
-            function_path: Path::new("tests/fixtures/build/js_function.wasm").to_path_buf(),
+            function: Path::new("tests/fixtures/build/js_function.wasm").into(),
             input,
             export: DEFAULT_EXPORT,
             ..Default::default()
@@ -266,7 +985,7 @@ mod tests {
     #[test]
     fn test_exit_code_zero() {
         let function_run_result = run(FunctionRunParams {
-            function_path: Path::new("tests/fixtures/build/exit_code.wasm").to_path_buf(),
+            function: Path::new("tests/fixtures/build/exit_code.wasm").into(),
             input: json!({ "code": 0 }).to_string().into(),
             export: DEFAULT_EXPORT,
             ..Default::default()
@@ -279,22 +998,30 @@ mod tests {
     #[test]
     fn test_exit_code_one() {
         let function_run_result = run(FunctionRunParams {
-            function_path: Path::new("tests/fixtures/build/exit_code.wasm").to_path_buf(),
+            function: Path::new("tests/fixtures/build/exit_code.wasm").into(),
             input: json!({ "code": 1 }).to_string().into(),
             export: DEFAULT_EXPORT,
             ..Default::default()
         })
         .unwrap();
 
-        assert_eq!(function_run_result.logs, "module exited with code: 1");
+        assert_eq!(function_run_result.logs, "");
+        assert_eq!(
+            function_run_result.outcome,
+            FunctionOutcome::Exited { code: 1 }
+        );
     }
 
     #[test]
     fn test_linear_memory_usage_in_kb() {
         let function_run_result = run(FunctionRunParams {
-            function_path: Path::new("tests/fixtures/build/linear_memory.wasm").to_path_buf(),
+            function: Path::new("tests/fixtures/build/linear_memory.wasm").into(),
             input: "{}".as_bytes().to_vec(),
             export: DEFAULT_EXPORT,
+            options: RunOptions {
+                memory_limit: None,
+                ..Default::default()
+            },
             ..Default::default()
         })
         .unwrap();
@@ -306,8 +1033,7 @@ mod tests {
     fn test_logs_truncation() {
         let input = "{}".as_bytes().to_vec();
         let function_run_result = run(FunctionRunParams {
-            function_path: Path::new("tests/fixtures/build/log_truncation_function.wasm")
-                .to_path_buf(),
+            function: Path::new("tests/fixtures/build/log_truncation_function.wasm").into(),
             input,
             export: DEFAULT_EXPORT,
             ..Default::default()
@@ -324,12 +1050,329 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_instructions_limit_enforced() {
+        let function_run_result = run(FunctionRunParams {
+            function: Path::new("tests/fixtures/build/infinite_loop.wasm").into(),
+            input: "{}".as_bytes().to_vec(),
+            export: DEFAULT_EXPORT,
+            options: RunOptions {
+                enforce_instructions_limit: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            function_run_result.outcome,
+            FunctionOutcome::InstructionLimitExceeded { limit: 11_000_000 }
+        );
+        assert_eq!(function_run_result.instructions, 11_000_000);
+        assert_eq!(function_run_result.logs, "looping forever\n");
+    }
+
+    #[test]
+    fn test_timeout() {
+        let function_run_result = run(FunctionRunParams {
+            function: Path::new("tests/fixtures/build/infinite_loop.wasm").into(),
+            input: "{}".as_bytes().to_vec(),
+            export: DEFAULT_EXPORT,
+            options: RunOptions {
+                timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            function_run_result.outcome,
+            FunctionOutcome::TimedOut { timeout_ms: 100 }
+        );
+        assert_eq!(function_run_result.logs, "looping forever\n");
+    }
+
+    #[test]
+    fn test_memory_limit_enforced() {
+        let function_run_result = run(FunctionRunParams {
+            function: Path::new("tests/fixtures/build/linear_memory.wasm").into(),
+            input: "{}".as_bytes().to_vec(),
+            export: DEFAULT_EXPORT,
+            // the production limit by default
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            function_run_result.outcome,
+            FunctionOutcome::MemoryLimitExceeded {
+                limit: DEFAULT_LINEAR_MEMORY_LIMIT
+            }
+        );
+        assert_eq!(function_run_result.memory_usage, 0);
+    }
+
+    #[test]
+    fn test_memory_usage_per_memory() {
+        let input = include_bytes!("../tests/fixtures/input/js_function_input.json").to_vec();
+        let function_run_result = run(FunctionRunParams {
+            function: Path::new("tests/fixtures/build/js_function.wasm").into(),
+            input,
+            export: DEFAULT_EXPORT,
+            options: RunOptions {
+                memory_limit: Some(DEFAULT_LINEAR_MEMORY_LIMIT),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(function_run_result.outcome, FunctionOutcome::Success);
+        let provider_memory = function_run_result
+            .memories
+            .iter()
+            .find(|memory| memory.instance == "javy_quickjs_provider_v1")
+            .expect("Expected the provider's memory to be tracked");
+        assert_eq!(provider_memory.index, 0);
+        assert_eq!(provider_memory.peak, Some(1280 * 1024));
+        assert_eq!(Some(provider_memory.initial), provider_memory.peak);
+        assert_eq!(function_run_result.memory_grow_calls, 0);
+    }
+
+    #[test]
+    fn test_memory_grow_calls() {
+        let function_run_result = run(FunctionRunParams {
+            function: Path::new("tests/fixtures/build/memory_grow.wasm").into(),
+            input: "{}".as_bytes().to_vec(),
+            export: DEFAULT_EXPORT,
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            function_run_result.memories,
+            vec![MemoryUsage {
+                instance: "Function".to_string(),
+                index: 0,
+                initial: 64 * 1024,
+                peak: Some(4 * 64 * 1024),
+            }]
+        );
+        assert_eq!(function_run_result.memory_grow_calls, 2);
+        assert_eq!(function_run_result.memory_usage, 4 * 64);
+    }
+
+    #[test]
+    fn test_memory_peaks_with_memories_of_equal_size() -> Result<(), Error> {
+        let run_wat = |wat: String| {
+            run(FunctionRunParams {
+                function: FunctionSource::Bytes {
+                    name: "two_memories.wat".to_string(),
+                    bytes: wat.into_bytes(),
+                },
+                input: b"{}".to_vec(),
+                export: DEFAULT_EXPORT,
+                ..Default::default()
+            })
+        };
+
+        for (growing, index) in [("$a", 0), ("$b", 1)] {
+            let function_run_result = run_wat(format!(
+                r#"(module
+                    (memory $a (export "a") 1)
+                    (memory $b (export "b") 1)
+                    (func (export "_start")
+                        (drop (memory.grow {growing} (i32.const 1)))
+                        (drop (memory.grow {growing} (i32.const 1))))
+                )"#
+            ))?;
+
+            let untouched = MemoryUsage {
+                instance: "Function".to_string(),
+                index: 1 - index,
+                initial: 64 * 1024,
+                peak: Some(64 * 1024),
+            };
+            let grown = MemoryUsage {
+                index,
+                peak: Some(3 * 64 * 1024),
+                ..untouched.clone()
+            };
+            let mut expected = vec![untouched, grown];
+            expected.sort_by_key(|memory| memory.index);
+            assert_eq!(function_run_result.memories, expected, "growing {growing}");
+            assert_eq!(function_run_result.memory_grow_calls, 2);
+            assert_eq!(function_run_result.memory_usage, 3 * 64);
+        }
+
+        // the peak of a memory that isn't exported can't be read
+        let function_run_result = run_wat(
+            r#"(module
+                (memory $a (export "a") 1)
+                (memory $b 1)
+                (func (export "_start")
+                    (drop (memory.grow $b (i32.const 1))))
+            )"#
+            .to_string(),
+        )?;
+        assert_eq!(function_run_result.memories[0].peak, Some(64 * 1024));
+        assert_eq!(function_run_result.memories[1].peak, None);
+        assert_eq!(function_run_result.memory_usage, 2 * 64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_limit_denies_growth() {
+        let function_run_result = run(FunctionRunParams {
+            function: Path::new("tests/fixtures/build/memory_grow.wasm").into(),
+            input: "{}".as_bytes().to_vec(),
+            export: DEFAULT_EXPORT,
+            options: RunOptions {
+                memory_limit: Some(3 * 64 * 1024),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            function_run_result.outcome,
+            FunctionOutcome::MemoryLimitExceeded {
+                limit: 3 * 64 * 1024
+            }
+        );
+        assert_eq!(function_run_result.memories[0].peak, Some(2 * 64 * 1024));
+        assert_eq!(function_run_result.memory_grow_calls, 2);
+    }
+
+    #[test]
+    fn test_function_runner_runs_many_times() -> Result<()> {
+        let runner = FunctionRunner::from_path("tests/fixtures/build/exit_code.wasm", None)?;
+
+        let success = runner.run(json!({ "code": 0 }).to_string().into(), DEFAULT_EXPORT)?;
+        assert_eq!(success.outcome, FunctionOutcome::Success);
+
+        let failure = runner.run(json!({ "code": 1 }).to_string().into(), DEFAULT_EXPORT)?;
+        assert_eq!(failure.outcome, FunctionOutcome::Exited { code: 1 });
+        assert!(failure
+            .error()
+            .is_some_and(|error| error.is_function_error()));
+        assert_eq!(failure.name, "exit_code.wasm");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_runner_is_thread_safe() -> Result<()> {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FunctionRunner>();
+
+        let bytes = std::fs::read("tests/fixtures/build/js_function.wasm")?;
+        let runner = FunctionRunner::from_bytes("js_function.wasm", &bytes, None)?;
+        let input = include_bytes!("../tests/fixtures/input/js_function_input.json");
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| runner.run(input.to_vec(), DEFAULT_EXPORT)))
+                .collect();
+
+            for handle in handles {
+                let function_run_result = handle.join().unwrap()?;
+                assert_eq!(function_run_result.outcome, FunctionOutcome::Success);
+                assert_eq!(function_run_result.memory_usage, 1280);
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_timeout_with_shared_ticker() -> Result<()> {
+        let runner = FunctionRunner::from_path("tests/fixtures/build/infinite_loop.wasm", None)?;
+        let options = RunOptions {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let function_run_result =
+                runner.run_with_options("{}".as_bytes().to_vec(), DEFAULT_EXPORT, &options)?;
+            assert_eq!(
+                function_run_result.outcome,
+                FunctionOutcome::TimedOut { timeout_ms: 50 }
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytes_source() -> Result<()> {
+        let bytes = std::fs::read("tests/fixtures/build/exit_code.wasm")?;
+        let size = bytes.len() as u64 / 1024;
+        let function_run_result = run(FunctionRunParams {
+            function: FunctionSource::Bytes {
+                name: "in_memory.wasm".to_string(),
+                bytes,
+            },
+            input: json!({ "code": 0 }).to_string().into(),
+            export: DEFAULT_EXPORT,
+            ..Default::default()
+        })?;
+
+        assert_eq!(function_run_result.name, "in_memory.wasm");
+        assert_eq!(function_run_result.size, size);
+        assert_eq!(function_run_result.outcome, FunctionOutcome::Success);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wat_bytes_source() -> Result<()> {
+        let function_run_result = run(FunctionRunParams {
+            function: FunctionSource::Bytes {
+                name: "inline.wat".to_string(),
+                bytes: br#"(module (func (export "_start")))"#.to_vec(),
+            },
+            input: "{}".as_bytes().to_vec(),
+            export: DEFAULT_EXPORT,
+            ..Default::default()
+        })?;
+
+        assert_eq!(function_run_result.outcome, FunctionOutcome::Success);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_source() -> Result<()> {
+        let engine = new_engine()?;
+        let module = Module::from_file(&engine, "tests/fixtures/build/exit_code.wasm")?;
+        let function_run_result = run(FunctionRunParams {
+            function: FunctionSource::Module {
+                name: "precompiled".to_string(),
+                module,
+            },
+            input: json!({ "code": 0 }).to_string().into(),
+            export: DEFAULT_EXPORT,
+            ..Default::default()
+        })?;
+
+        assert_eq!(function_run_result.name, "precompiled");
+        assert_eq!(function_run_result.size, 0);
+        assert_eq!(function_run_result.outcome, FunctionOutcome::Success);
+
+        Ok(())
+    }
+
     #[test]
     fn test_file_size_in_kb() {
         let file_path = Path::new("tests/fixtures/build/exit_code.wasm");
 
         let function_run_result = run(FunctionRunParams {
-            function_path: file_path.to_path_buf(),
+            function: file_path.into(),
             input: json!({ "code": 0 }).to_string().into(),
             export: DEFAULT_EXPORT,
             ..Default::default()
@@ -341,4 +1384,245 @@ mod tests {
             file_path.metadata().unwrap().len() / 1024
         );
     }
+
+    #[test]
+    fn test_disallowed_imports() -> Result<()> {
+        let wat = br#"(module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "env" "abort" (func $abort))
+            (func $open_file
+                (drop (call $path_open (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i64.const 0) (i64.const 0) (i32.const 0) (i32.const 0))))
+            (func (export "_start") (call $open_file))
+        )"#;
+        let error = run(FunctionRunParams {
+            function: FunctionSource::Bytes {
+                name: "open.wat".to_string(),
+                bytes: wat.to_vec(),
+            },
+            input: b"{}".to_vec(),
+            export: DEFAULT_EXPORT,
+            ..Default::default()
+        })
+        .unwrap_err()
+        .to_string();
+
+        assert!(
+            error.contains("wasi_snapshot_preview1::path_open (import #0) isn't allowed by the limit profile, called from open_file"),
+            "{error}"
+        );
+        assert!(
+            error.contains(
+                "env::abort (import #1) isn't allowed by the limit profile, \"env\" is neither"
+            ),
+            "{error}"
+        );
+
+        let limit_profile = LimitProfile {
+            allowed_imports: vec!["wasi_snapshot_preview1::*".to_string()],
+            ..LimitProfile::default()
+        };
+        let error = run(FunctionRunParams {
+            function: FunctionSource::Bytes {
+                name: "open.wat".to_string(),
+                bytes: wat.to_vec(),
+            },
+            input: b"{}".to_vec(),
+            export: DEFAULT_EXPORT,
+            options: RunOptions {
+                limit_profile: Some(&limit_profile),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap_err()
+        .to_string();
+        assert!(!error.contains("path_open"), "{error}");
+
+        // imports the runner can link are only reported, unless the profile enforces its allowed imports
+        let wat = br#"(module
+            (import "wasi_snapshot_preview1" "path_open" (func (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (func (export "_start"))
+        )"#;
+        let run_wat = |limit_profile: &LimitProfile| {
+            run(FunctionRunParams {
+                function: FunctionSource::Bytes {
+                    name: "open.wat".to_string(),
+                    bytes: wat.to_vec(),
+                },
+                input: b"{}".to_vec(),
+                export: DEFAULT_EXPORT,
+                options: RunOptions {
+                    limit_profile: Some(limit_profile),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        };
+        let function_run_result = run_wat(&LimitProfile::default())?;
+        assert_eq!(function_run_result.outcome, FunctionOutcome::Success);
+        assert_eq!(
+            function_run_result.disallowed_imports,
+            ["wasi_snapshot_preview1::path_open (import #0) isn't allowed by the limit profile"]
+        );
+
+        let limit_profile = LimitProfile {
+            enforce_imports: true,
+            ..LimitProfile::default()
+        };
+        let error = run_wat(&limit_profile).unwrap_err();
+        assert!(matches!(error, Error::Link { .. }), "{error}");
+
+        // a runner checks the imports once per profile
+        let runner = FunctionRunner::from_bytes("open.wat", wat, None)?;
+        for _ in 0..2 {
+            let error = runner
+                .run_with_options(
+                    b"{}".to_vec(),
+                    DEFAULT_EXPORT,
+                    &RunOptions {
+                        limit_profile: Some(&limit_profile),
+                        ..Default::default()
+                    },
+                )
+                .unwrap_err();
+            assert!(matches!(error, Error::Link { .. }), "{error}");
+            let function_run_result = runner.run(b"{}".to_vec(), DEFAULT_EXPORT)?;
+            assert_eq!(function_run_result.disallowed_imports.len(), 1);
+        }
+        assert_eq!(runner.checked_imports.lock().unwrap().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_provider_error() {
+        let error = run(FunctionRunParams {
+            function: FunctionSource::Bytes {
+                name: "provider.wat".to_string(),
+                bytes: br#"(module
+                    (import "javy_quickjs_provider_v9" "eval_bytecode" (func (param i32 i32)))
+                    (func (export "_start"))
+                )"#
+                .to_vec(),
+            },
+            input: b"{}".to_vec(),
+            export: DEFAULT_EXPORT,
+            ..Default::default()
+        })
+        .unwrap_err()
+        .to_string();
+        assert!(
+            error.contains("unknown provider \"javy_quickjs_provider_v9\" imported for eval_bytecode, available providers are: javy_quickjs_provider_v1, javy_quickjs_provider_v2, javy_quickjs_provider_v3"),
+            "{error}"
+        );
+    }
+
+    fn binary_output_function(data: &str) -> FunctionSource {
+        FunctionSource::Bytes {
+            name: "binary_output.wat".to_string(),
+            bytes: format!(
+                r#"(module
+                    (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 16) "{data}")
+                    (func (export "_start")
+                        (i32.store (i32.const 0) (i32.const 16))
+                        (i32.store (i32.const 4) (i32.const {}))
+                        (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))
+                )"#,
+                data.len() / 3
+            )
+            .into_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_binary_and_messagepack_output() -> Result<(), Error> {
+        let function_run_result = run(FunctionRunParams {
+            function: binary_output_function("\\ff"),
+            input: b"{}".to_vec(),
+            export: DEFAULT_EXPORT,
+            ..Default::default()
+        })?;
+        let FunctionOutput::InvalidJsonOutput(invalid_output) = function_run_result.output else {
+            panic!("Expected invalid output");
+        };
+        assert_eq!(invalid_output.stdout, "ff");
+        assert_eq!(invalid_output.offset, Some(0));
+
+        let function_run_result = run(FunctionRunParams {
+            function: binary_output_function("\\81\\a1\\61\\01"),
+            input: vec![0x81, 0xa1, 0x62, 0xc3],
+            export: DEFAULT_EXPORT,
+            options: RunOptions {
+                input_codec: Some(&MessagePackCodec),
+                output_codec: Some(&MessagePackCodec),
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+        assert_eq!(function_run_result.input, json!({"b": true}).into());
+        assert_eq!(function_run_result.input_size(), 4);
+        assert_eq!(
+            serde_json::to_value(&function_run_result.output).unwrap(),
+            json!({"a": 1})
+        );
+        assert_eq!(function_run_result.output_size(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        let error = FunctionRunner::from_path("tests/fixtures/build/missing.wasm", None)
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::Load { .. }), "{error}");
+
+        let error = FunctionRunner::from_bytes("invalid.wasm", b"\0asm", None)
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::Compile { .. }), "{error}");
+
+        let runner = FunctionRunner::from_bytes(
+            "unreachable.wat",
+            br#"(module (func (export "_start") unreachable))"#,
+            None,
+        )
+        .unwrap();
+        let error = runner.run(b"{}".to_vec(), "run").unwrap_err();
+        assert!(
+            matches!(&error, Error::Export { export, .. } if export == "run"),
+            "{error}"
+        );
+        assert!(!error.to_string().contains("function-runner inspect"));
+
+        let error = runner
+            .run_with_options(
+                b"{".to_vec(),
+                DEFAULT_EXPORT,
+                &RunOptions {
+                    encode_input: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidInput { .. }), "{error}");
+
+        let function_run_result = runner.run(b"{".to_vec(), DEFAULT_EXPORT).unwrap();
+        assert_eq!(
+            function_run_result.input,
+            FunctionInput::BinaryInput(BinaryInput {
+                bytes: b"{".to_vec()
+            })
+        );
+        assert_eq!(function_run_result.input_size(), 1);
+        assert!(matches!(
+            function_run_result.outcome,
+            FunctionOutcome::Trapped {
+                kind: TrapKind::UnreachableCodeReached,
+                ..
+            }
+        ));
+    }
 }
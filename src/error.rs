@@ -0,0 +1,51 @@
+use thiserror::Error as ThisError;
+
+use crate::function_run_result::FunctionOutcome;
+
+/// Why running a Function failed: either the runner couldn't run it, or the Function itself didn't complete.
+#[derive(ThisError, Debug)]
+pub enum Error {
+    /// The Function's module couldn't be read.
+    #[error("Couldn't load the Function {name:?}: {message}")]
+    Load { name: String, message: String },
+    /// The Function's module, or a provider it imports, isn't valid Wasm.
+    #[error("Couldn't compile {name:?}: {message}")]
+    Compile { name: String, message: String },
+    /// The Function's imports or its export couldn't be resolved.
+    #[error("Couldn't link the Function {name:?}: {message}")]
+    Link { name: String, message: String },
+    /// The export to run is missing, or isn't a function taking and returning nothing.
+    #[error("Couldn't call {export:?} in the Function {name:?}: {message}")]
+    Export {
+        name: String,
+        export: String,
+        message: String,
+    },
+    /// The input couldn't be prepared for the Function, e.g. a JSON input file isn't valid JSON.
+    #[error("{message}")]
+    InvalidInput { message: String },
+    #[error("{0}")]
+    SchemaParse(String),
+    #[error("{0}")]
+    QueryParse(String),
+    #[error("Unable to analyze scale limits: {0}")]
+    ScaleLimits(String),
+    /// A failure of the runner itself, such as creating the engine.
+    #[error("{0}")]
+    Internal(String),
+    /// The Function ran but didn't complete, never [`FunctionOutcome::Success`].
+    ///
+    /// Runs never fail with it, they report how the Function ended in the result's outcome. It is only built by
+    /// [`crate::function_run_result::FunctionRunResult::error`], for callers treating an incomplete run as an error.
+    #[error("The Function {0}")]
+    Function(FunctionOutcome),
+}
+
+impl Error {
+    /// Whether the error comes from the Function's own execution rather than from the runner.
+    pub fn is_function_error(&self) -> bool {
+        matches!(self, Error::Function(_))
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,434 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    batch::BatchEntry,
+    function_run_result::{FunctionOutcome, FunctionOutput},
+};
+
+pub const INPUT_FILE_NAME: &str = "input.json";
+pub const EXPECTED_FILE_NAME: &str = "expected.json";
+
+/// A directory containing an `input.json` and the `expected.json` output of the Function for it.
+pub struct FixtureCase {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl FixtureCase {
+    pub fn input_path(&self) -> PathBuf {
+        self.dir.join(INPUT_FILE_NAME)
+    }
+
+    pub fn expected_path(&self) -> PathBuf {
+        self.dir.join(EXPECTED_FILE_NAME)
+    }
+
+    pub fn read_expected(&self) -> Result<Value> {
+        let path = self.expected_path();
+        let contents = std::fs::read(&path)
+            .map_err(|e| anyhow!("Couldn't load expected output {:?}: {}", path, e))?;
+
+        serde_json::from_slice(&contents)
+            .map_err(|e| anyhow!("Invalid expected output JSON {:?}: {}", path, e))
+    }
+
+    pub fn write_expected(&self, output: &Value) -> Result<()> {
+        let path = self.expected_path();
+        let contents = serde_json::to_string_pretty(output)? + "\n";
+
+        std::fs::write(&path, contents)
+            .map_err(|e| anyhow!("Couldn't write expected output {:?}: {}", path, e))
+    }
+}
+
+/// Find every sub-directory of `dir` containing an `input.json`, sorted by name.
+pub fn discover_cases(dir: &Path) -> Result<Vec<FixtureCase>> {
+    let mut cases = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("Couldn't read cases directory {:?}: {}", dir, e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|path| path.join(INPUT_FILE_NAME).is_file())
+        .map(|path| FixtureCase {
+            name: path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+            dir: path,
+        })
+        .collect::<Vec<_>>();
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(cases)
+}
+
+/// A difference between the expected and actual output, located by its JSON pointer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonDifference {
+    Missing {
+        path: String,
+        expected: Value,
+    },
+    Unexpected {
+        path: String,
+        actual: Value,
+    },
+    Changed {
+        path: String,
+        expected: Value,
+        actual: Value,
+    },
+}
+
+impl fmt::Display for JsonDifference {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonDifference::Missing { path, expected } => {
+                write!(
+                    formatter,
+                    "{}: missing, expected {}",
+                    display_path(path),
+                    expected
+                )
+            }
+            JsonDifference::Unexpected { path, actual } => {
+                write!(formatter, "{}: unexpected {}", display_path(path), actual)
+            }
+            JsonDifference::Changed {
+                path,
+                expected,
+                actual,
+            } => write!(
+                formatter,
+                "{}: expected {}, got {}",
+                display_path(path),
+                expected,
+                actual
+            ),
+        }
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+/// Compare two JSON values structurally, objects key by key and arrays element by element.
+pub fn diff_json(expected: &Value, actual: &Value) -> Vec<JsonDifference> {
+    let mut differences = Vec::new();
+    diff_json_at(String::new(), expected, actual, &mut differences);
+    differences
+}
+
+fn diff_json_at(
+    path: String,
+    expected: &Value,
+    actual: &Value,
+    differences: &mut Vec<JsonDifference>,
+) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected_value) in expected {
+                let key_path = format!("{path}/{}", escape_pointer_token(key));
+                match actual.get(key) {
+                    Some(actual_value) => {
+                        diff_json_at(key_path, expected_value, actual_value, differences)
+                    }
+                    None => differences.push(JsonDifference::Missing {
+                        path: key_path,
+                        expected: expected_value.clone(),
+                    }),
+                }
+            }
+            for (key, actual_value) in actual {
+                if !expected.contains_key(key) {
+                    differences.push(JsonDifference::Unexpected {
+                        path: format!("{path}/{}", escape_pointer_token(key)),
+                        actual: actual_value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for index in 0..expected.len().max(actual.len()) {
+                let index_path = format!("{path}/{index}");
+                match (expected.get(index), actual.get(index)) {
+                    (Some(expected_value), Some(actual_value)) => {
+                        diff_json_at(index_path, expected_value, actual_value, differences)
+                    }
+                    (Some(expected_value), None) => differences.push(JsonDifference::Missing {
+                        path: index_path,
+                        expected: expected_value.clone(),
+                    }),
+                    (None, Some(actual_value)) => differences.push(JsonDifference::Unexpected {
+                        path: index_path,
+                        actual: actual_value.clone(),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        (expected, actual) if expected != actual => differences.push(JsonDifference::Changed {
+            path,
+            expected: expected.clone(),
+            actual: actual.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// How a fixture case ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixtureStatus {
+    Passed,
+    /// The expected output was rewritten with the actual output.
+    Updated,
+    Failed(Vec<JsonDifference>),
+    /// The case couldn't be run or the Function didn't produce a comparable output.
+    Error(String),
+}
+
+pub struct FixtureResult {
+    pub name: String,
+    pub duration: Duration,
+    pub status: FixtureStatus,
+}
+
+impl FixtureResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, FixtureStatus::Passed | FixtureStatus::Updated)
+    }
+}
+
+/// Compare the output of the run against the case's expected output, or rewrite it when `update` is set.
+pub fn check_case(case: &FixtureCase, entry: &BatchEntry, update: bool) -> FixtureResult {
+    let status = match actual_output(entry) {
+        Err(error) => FixtureStatus::Error(error),
+        Ok(actual) if update => match case.write_expected(actual) {
+            Ok(()) => FixtureStatus::Updated,
+            Err(error) => FixtureStatus::Error(error.to_string()),
+        },
+        Ok(actual) => match case.read_expected() {
+            Err(error) => FixtureStatus::Error(error.to_string()),
+            Ok(expected) => {
+                let differences = diff_json(&expected, actual);
+                if differences.is_empty() {
+                    FixtureStatus::Passed
+                } else {
+                    FixtureStatus::Failed(differences)
+                }
+            }
+        },
+    };
+
+    FixtureResult {
+        name: case.name.clone(),
+        duration: entry.duration,
+        status,
+    }
+}
+
+fn actual_output(entry: &BatchEntry) -> Result<&Value, String> {
+    let result = entry.result.as_ref().map_err(ToString::to_string)?;
+
+    match &result.outcome {
+        FunctionOutcome::Success => {}
+        FunctionOutcome::Trapped { message, .. } => {
+            return Err(format!("Function {}: {}", result.outcome, message))
+        }
+        outcome => return Err(format!("Function {outcome}")),
+    }
+
+    match &result.output {
+        FunctionOutput::JsonOutput(output) => Ok(output),
+        FunctionOutput::InvalidJsonOutput(invalid_output) => {
+            Err(format!("Invalid output: {}", invalid_output.error))
+        }
+    }
+}
+
+/// One line per case, with the differences of failing cases, followed by the number of passing cases.
+pub fn summary(results: &[FixtureResult]) -> String {
+    let mut summary = String::new();
+    for result in results {
+        let _ = match &result.status {
+            FixtureStatus::Passed => writeln!(summary, "pass     {}", result.name),
+            FixtureStatus::Updated => writeln!(summary, "updated  {}", result.name),
+            FixtureStatus::Failed(differences) => {
+                let _ = writeln!(summary, "FAIL     {}", result.name);
+                differences
+                    .iter()
+                    .try_for_each(|difference| writeln!(summary, "    {difference}"))
+            }
+            FixtureStatus::Error(error) => writeln!(summary, "ERROR    {}: {error}", result.name),
+        };
+    }
+
+    let passed = results.iter().filter(|result| result.is_success()).count();
+    let _ = write!(summary, "\n{passed}/{} passed", results.len());
+
+    summary
+}
+
+/// A JUnit XML report with one test suite named after the Function.
+pub fn junit_report(suite_name: &str, results: &[FixtureResult]) -> String {
+    let failures = results
+        .iter()
+        .filter(|result| matches!(result.status, FixtureStatus::Failed(_)))
+        .count();
+    let errors = results
+        .iter()
+        .filter(|result| matches!(result.status, FixtureStatus::Error(_)))
+        .count();
+    let time: Duration = results.iter().map(|result| result.duration).sum();
+
+    let mut report = String::new();
+    let _ = writeln!(report, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(report, "<testsuites>");
+    let _ = writeln!(
+        report,
+        r#"  <testsuite name="{}" tests="{}" failures="{failures}" errors="{errors}" time="{:.3}">"#,
+        escape_xml(suite_name),
+        results.len(),
+        time.as_secs_f64(),
+    );
+
+    for result in results {
+        let _ = write!(
+            report,
+            r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+            escape_xml(&result.name),
+            escape_xml(suite_name),
+            result.duration.as_secs_f64(),
+        );
+        let _ = match &result.status {
+            FixtureStatus::Passed | FixtureStatus::Updated => writeln!(report, "/>"),
+            FixtureStatus::Failed(differences) => {
+                let details = differences
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                writeln!(
+                    report,
+                    ">\n      <failure message=\"output doesn't match {EXPECTED_FILE_NAME}\">{}</failure>\n    </testcase>",
+                    escape_xml(&details)
+                )
+            }
+            FixtureStatus::Error(error) => writeln!(
+                report,
+                ">\n      <error message=\"{}\"/>\n    </testcase>",
+                escape_xml(error)
+            ),
+        };
+    }
+
+    let _ = writeln!(report, "  </testsuite>");
+    let _ = writeln!(report, "</testsuites>");
+
+    report
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_json() {
+        let expected = json!({
+            "discounts": [{"value": 10, "targets": ["a", "b"]}],
+            "strategy": "FIRST",
+            "a/b": 1
+        });
+        let actual = json!({
+            "discounts": [{"value": 5, "targets": ["a"]}],
+            "extra": null,
+            "a/b": 1
+        });
+
+        let differences: Vec<String> = diff_json(&expected, &actual)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            differences,
+            [
+                "/discounts/0/targets/1: missing, expected \"b\"",
+                "/discounts/0/value: expected 10, got 5",
+                "/strategy: missing, expected \"FIRST\"",
+                "/extra: unexpected null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_json_equal() {
+        let value = json!({"discounts": [], "strategy": "FIRST"});
+        assert!(diff_json(&value, &value).is_empty());
+        assert_eq!(
+            diff_json(&json!(1), &json!("1")),
+            [JsonDifference::Changed {
+                path: String::new(),
+                expected: json!(1),
+                actual: json!("1")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let results = vec![
+            FixtureResult {
+                name: "passing".to_string(),
+                duration: Duration::from_millis(5),
+                status: FixtureStatus::Passed,
+            },
+            FixtureResult {
+                name: "failing".to_string(),
+                duration: Duration::from_millis(5),
+                status: FixtureStatus::Failed(diff_json(&json!({"a": 1}), &json!({"a": 2}))),
+            },
+            FixtureResult {
+                name: "erroring".to_string(),
+                duration: Duration::ZERO,
+                status: FixtureStatus::Error("Invalid input JSON <eof>".to_string()),
+            },
+        ];
+
+        let report = junit_report("function.wasm", &results);
+
+        assert!(report.contains(
+            r#"<testsuite name="function.wasm" tests="3" failures="1" errors="1" time="0.010">"#
+        ));
+        assert!(
+            report.contains(r#"<testcase name="passing" classname="function.wasm" time="0.005"/>"#)
+        );
+        assert!(report.contains("/a: expected 1, got 2</failure>"));
+        assert!(report.contains(r#"<error message="Invalid input JSON &lt;eof&gt;"/>"#));
+    }
+}
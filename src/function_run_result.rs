@@ -2,12 +2,22 @@ use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-const FUNCTION_LOG_LIMIT: usize = 1_000;
+use crate::{
+    codec::{Codec, DecodeError, JsonCodec},
+    error::Error,
+    limit_profile::LimitProfile,
+};
+
+pub const FUNCTION_LOG_LIMIT: usize = 1_000;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct InvalidOutput {
     pub error: String,
+    /// The output as text, or as hex bytes when it is binary.
     pub stdout: String,
+    /// Byte offset in the output where decoding failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -17,24 +27,365 @@ pub enum FunctionOutput {
     InvalidJsonOutput(InvalidOutput),
 }
 
+/// Space separated hex bytes, e.g. `81 a1 61 01`.
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl FunctionOutput {
+    /// Decode the bytes the Function wrote to stdout with the output codec.
+    pub fn decode(bytes: &[u8], codec: &dyn Codec) -> Self {
+        match codec.decode(bytes) {
+            Ok(value) => FunctionOutput::JsonOutput(value),
+            Err(DecodeError { message, offset }) => {
+                FunctionOutput::InvalidJsonOutput(InvalidOutput {
+                    error: message,
+                    stdout: match std::str::from_utf8(bytes) {
+                        Ok(stdout) if !codec.is_binary() => stdout.to_string(),
+                        _ => hex_bytes(bytes),
+                    },
+                    offset: Some(offset),
+                })
+            }
+        }
+    }
+}
+
+/// Input that couldn't be decoded, serialized as base64.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BinaryInput {
+    #[serde(rename = "base64", with = "base64_bytes")]
+    pub bytes: Vec<u8>,
+}
+
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// The input passed to the Function, as JSON when it could be decoded.
+///
+/// In a run result it is serialized as two fields, so JSON input is never mistaken for binary input: `input` with
+/// the JSON, `null` for binary input, and `raw_input` with the bytes of binary input only.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "InputFields", into = "InputFields")]
+pub enum FunctionInput {
+    BinaryInput(BinaryInput),
+    JsonInput(serde_json::Value),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct InputFields {
+    input: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw_input: Option<BinaryInput>,
+}
+
+impl From<InputFields> for FunctionInput {
+    fn from(fields: InputFields) -> Self {
+        match fields.raw_input {
+            Some(binary_input) => FunctionInput::BinaryInput(binary_input),
+            None => FunctionInput::JsonInput(fields.input),
+        }
+    }
+}
+
+impl From<FunctionInput> for InputFields {
+    fn from(input: FunctionInput) -> Self {
+        match input {
+            FunctionInput::BinaryInput(binary_input) => InputFields {
+                input: serde_json::Value::Null,
+                raw_input: Some(binary_input),
+            },
+            FunctionInput::JsonInput(value) => InputFields {
+                input: value,
+                raw_input: None,
+            },
+        }
+    }
+}
+
+impl FunctionInput {
+    /// Decode the bytes passed to the Function with the input codec, keeping them as they are when they can't be.
+    pub fn decode(bytes: &[u8], codec: &dyn Codec) -> Self {
+        match codec.decode(bytes) {
+            Ok(value) => FunctionInput::JsonInput(value),
+            Err(_) => FunctionInput::BinaryInput(BinaryInput {
+                bytes: bytes.to_vec(),
+            }),
+        }
+    }
+
+    /// Size of the input once encoded, JSON input is measured minified.
+    fn size(&self) -> usize {
+        match self {
+            FunctionInput::BinaryInput(binary_input) => binary_input.bytes.len(),
+            FunctionInput::JsonInput(value) => get_json_size_as_bytes(value),
+        }
+    }
+}
+
+impl From<serde_json::Value> for FunctionInput {
+    fn from(value: serde_json::Value) -> Self {
+        FunctionInput::JsonInput(value)
+    }
+}
+
+/// The kind of trap that stopped the Function, see [`wasmtime::Trap`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrapKind {
+    StackOverflow,
+    MemoryOutOfBounds,
+    HeapMisaligned,
+    TableOutOfBounds,
+    IndirectCallToNull,
+    BadSignature,
+    IntegerOverflow,
+    IntegerDivisionByZero,
+    BadConversionToInteger,
+    UnreachableCodeReached,
+    Interrupt,
+    OutOfFuel,
+    NullReference,
+    /// An error returned by a host function, such as a WASI call, rather than a trap of the Function's code.
+    Host,
+    /// A trap this version of the runner doesn't know about.
+    Unknown,
+}
+
+impl From<wasmtime::Trap> for TrapKind {
+    fn from(trap: wasmtime::Trap) -> Self {
+        match trap {
+            wasmtime::Trap::StackOverflow => TrapKind::StackOverflow,
+            wasmtime::Trap::MemoryOutOfBounds => TrapKind::MemoryOutOfBounds,
+            wasmtime::Trap::HeapMisaligned => TrapKind::HeapMisaligned,
+            wasmtime::Trap::TableOutOfBounds => TrapKind::TableOutOfBounds,
+            wasmtime::Trap::IndirectCallToNull => TrapKind::IndirectCallToNull,
+            wasmtime::Trap::BadSignature => TrapKind::BadSignature,
+            wasmtime::Trap::IntegerOverflow => TrapKind::IntegerOverflow,
+            wasmtime::Trap::IntegerDivisionByZero => TrapKind::IntegerDivisionByZero,
+            wasmtime::Trap::BadConversionToInteger => TrapKind::BadConversionToInteger,
+            wasmtime::Trap::UnreachableCodeReached => TrapKind::UnreachableCodeReached,
+            wasmtime::Trap::Interrupt => TrapKind::Interrupt,
+            wasmtime::Trap::OutOfFuel => TrapKind::OutOfFuel,
+            wasmtime::Trap::NullReference => TrapKind::NullReference,
+            _ => TrapKind::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for TrapKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            TrapKind::StackOverflow => "stack overflow",
+            TrapKind::MemoryOutOfBounds => "out of bounds memory access",
+            TrapKind::HeapMisaligned => "misaligned memory access",
+            TrapKind::TableOutOfBounds => "out of bounds table access",
+            TrapKind::IndirectCallToNull => "indirect call to null",
+            TrapKind::BadSignature => "indirect call type mismatch",
+            TrapKind::IntegerOverflow => "integer overflow",
+            TrapKind::IntegerDivisionByZero => "integer divide by zero",
+            TrapKind::BadConversionToInteger => "invalid conversion to integer",
+            TrapKind::UnreachableCodeReached => "unreachable instruction executed",
+            TrapKind::Interrupt => "interrupted",
+            TrapKind::OutOfFuel => "out of fuel",
+            TrapKind::NullReference => "null reference",
+            TrapKind::Host => "host error",
+            TrapKind::Unknown => "unknown trap",
+        };
+
+        write!(formatter, "{description}")
+    }
+}
+
+/// How the Function's execution ended.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FunctionOutcome {
+    /// The Function ran to completion.
+    #[default]
+    Success,
+    /// The Function exited with a non-zero code.
+    Exited { code: i32 },
+    /// The Function trapped, or a host function it called failed.
+    Trapped { kind: TrapKind, message: String },
+    /// The Function used up the scaled instruction limit (its fuel ran out).
+    InstructionLimitExceeded { limit: u64 },
+    /// The Function was interrupted after running longer than the wall-clock timeout.
+    TimedOut { timeout_ms: u64 },
+    /// Growing a linear memory beyond the memory limit was denied and the Function failed.
+    MemoryLimitExceeded { limit: u64 },
+}
+
+impl fmt::Display for FunctionOutcome {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FunctionOutcome::Success => write!(formatter, "success"),
+            FunctionOutcome::Exited { code } => write!(formatter, "exited with code {code}"),
+            FunctionOutcome::Trapped { kind, .. } => write!(formatter, "trapped: {kind}"),
+            FunctionOutcome::InstructionLimitExceeded { .. } => {
+                write!(formatter, "instruction limit exceeded")
+            }
+            FunctionOutcome::TimedOut { .. } => write!(formatter, "timed out"),
+            FunctionOutcome::MemoryLimitExceeded { .. } => {
+                write!(formatter, "memory limit exceeded")
+            }
+        }
+    }
+}
+
+/// Usage of a single linear memory, sizes are in bytes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The instance owning the memory, `Function` for the module itself or the name of a provider.
+    pub instance: String,
+    /// Index of the memory among the memories defined by its instance.
+    pub index: u32,
+    pub initial: u64,
+    /// Read from the memory once the Function has run, `None` when its instance doesn't export it.
+    pub peak: Option<u64>,
+}
+
+/// A resource with a production limit.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitedResource {
+    Instructions,
+    InputSize,
+    OutputSize,
+    Logs,
+    Memory,
+}
+
+impl fmt::Display for LimitedResource {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitedResource::Instructions => write!(formatter, "instructions"),
+            LimitedResource::InputSize => write!(formatter, "input size"),
+            LimitedResource::OutputSize => write!(formatter, "output size"),
+            LimitedResource::Logs => write!(formatter, "logs length"),
+            LimitedResource::Memory => write!(formatter, "linear memory"),
+        }
+    }
+}
+
+/// A resource used beyond its limit, sizes are in bytes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LimitViolation {
+    pub resource: LimitedResource,
+    pub used: u64,
+    pub limit: u64,
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} exceeded: used {}, limit is {}",
+            self.resource, self.used, self.limit
+        )
+    }
+}
+
+/// Limits for each resource, scaled for the run where the limit depends on the input. Sizes are in
+/// bytes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub instructions: u64,
+    pub input_size: u64,
+    pub output_size: u64,
+    pub logs: u64,
+    pub memory: u64,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        LimitProfile::default().scaled(1.0)
+    }
+}
+
+/// Percentage of each resource's limit used by the run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct BudgetUsage {
+    pub instructions: f64,
+    pub input_size: f64,
+    pub output_size: f64,
+    pub logs: f64,
+    pub memory: f64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FunctionRunResult {
     pub name: String,
     pub size: u64,
     pub memory_usage: u64,
+    #[serde(default)]
+    pub memories: Vec<MemoryUsage>,
+    /// Calls growing any of the memories, they can't be told apart by memory.
+    #[serde(default)]
+    pub memory_grow_calls: u64,
     pub instructions: u64,
     pub logs: String,
-    pub input: serde_json::Value,
+    #[serde(flatten)]
+    pub input: FunctionInput,
+    /// Name of the codec the input was encoded with.
+    #[serde(default = "default_codec")]
+    pub input_codec: String,
+    /// Size of the input as passed to the Function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_input_size: Option<usize>,
     pub output: FunctionOutput,
+    /// Name of the codec the output was decoded with.
+    #[serde(default = "default_codec")]
+    pub output_codec: String,
+    /// Size of the output as written by the Function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_output_size: Option<usize>,
+    #[serde(default)]
+    pub outcome: FunctionOutcome,
+    #[serde(default = "default_scale_factor")]
+    pub scale_factor: f64,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    #[serde(default)]
+    pub budget_used: BudgetUsage,
+    #[serde(default)]
+    pub violations: Vec<LimitViolation>,
+    /// Imports the limit profile doesn't allow, reported when it doesn't enforce them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disallowed_imports: Vec<String>,
     #[serde(skip)]
     pub profile: Option<String>,
-    #[serde(skip)]
-    pub scale_factor: f64,
 }
 
-const DEFAULT_INSTRUCTIONS_LIMIT: u64 = 11_000_000;
-const DEFAULT_INPUT_SIZE_LIMIT: u64 = 64_000;
-const DEFAULT_OUTPUT_SIZE_LIMIT: u64 = 20_000;
+fn default_scale_factor() -> f64 {
+    1.0
+}
+
+fn default_codec() -> String {
+    JsonCodec.name().to_string()
+}
+
+pub const DEFAULT_INSTRUCTIONS_LIMIT: u64 = 11_000_000;
+pub const DEFAULT_INPUT_SIZE_LIMIT: u64 = 64_000;
+pub const DEFAULT_OUTPUT_SIZE_LIMIT: u64 = 20_000;
+pub const DEFAULT_LINEAR_MEMORY_LIMIT: u64 = 10_000_000;
 
 pub fn get_json_size_as_bytes(value: &serde_json::Value) -> usize {
     serde_json::to_vec(value).map(|v| v.len()).unwrap_or(0)
@@ -45,16 +396,126 @@ impl FunctionRunResult {
         serde_json::to_string_pretty(&self).unwrap_or_else(|error| error.to_string())
     }
 
+    /// Size of the input on the wire, measured on the minified JSON when the size passed isn't known.
     pub fn input_size(&self) -> usize {
-        get_json_size_as_bytes(&self.input)
+        self.raw_input_size.unwrap_or_else(|| self.input.size())
     }
 
+    /// Size of the output on the wire, measured on the minified JSON for JSON output.
     pub fn output_size(&self) -> usize {
+        if let Some(raw_output_size) = self.raw_output_size {
+            return raw_output_size;
+        }
         match &self.output {
             FunctionOutput::JsonOutput(value) => get_json_size_as_bytes(value),
             FunctionOutput::InvalidJsonOutput(_value) => 0,
         }
     }
+
+    /// Judge the run against `limits`, filling in the limits, the budget used and the violations.
+    pub fn apply_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+        self.budget_used = self.budget_usage();
+        self.violations = self.limit_violations();
+    }
+
+    /// What the run used of each resource and the limit it is judged against, along with whether exceeding
+    /// that limit stopped the Function.
+    fn resource_usages(&self) -> [(LimitedResource, u64, u64, bool); 5] {
+        let memory_peak = self
+            .memories
+            .iter()
+            .filter_map(|memory| memory.peak)
+            .max()
+            .unwrap_or_default()
+            .max(self.memory_usage * 1024);
+        let (memory_limit, memory_limit_exceeded) = match self.outcome {
+            FunctionOutcome::MemoryLimitExceeded { limit } => (limit, true),
+            _ => (self.limits.memory, false),
+        };
+
+        [
+            (
+                LimitedResource::Instructions,
+                self.instructions,
+                self.limits.instructions,
+                matches!(
+                    self.outcome,
+                    FunctionOutcome::InstructionLimitExceeded { .. }
+                ),
+            ),
+            (
+                LimitedResource::InputSize,
+                self.input_size() as u64,
+                self.limits.input_size,
+                false,
+            ),
+            (
+                LimitedResource::OutputSize,
+                self.output_size() as u64,
+                self.limits.output_size,
+                false,
+            ),
+            (
+                LimitedResource::Logs,
+                self.logs.len() as u64,
+                self.limits.logs,
+                false,
+            ),
+            (
+                LimitedResource::Memory,
+                memory_peak,
+                memory_limit,
+                memory_limit_exceeded,
+            ),
+        ]
+    }
+
+    /// The percentage of each limit used, rounded to two decimals.
+    pub fn budget_usage(&self) -> BudgetUsage {
+        let [instructions, input_size, output_size, logs, memory] =
+            self.resource_usages().map(|(_, used, limit, _)| {
+                if limit == 0 {
+                    return 0.0;
+                }
+                (used as f64 / limit as f64 * 10_000.0).round() / 100.0
+            });
+
+        BudgetUsage {
+            instructions,
+            input_size,
+            output_size,
+            logs,
+            memory,
+        }
+    }
+
+    /// The resources used beyond their limits, including the limits that stopped the Function.
+    pub fn limit_violations(&self) -> Vec<LimitViolation> {
+        self.resource_usages()
+            .into_iter()
+            .filter(|(_, used, limit, stopped)| used > limit || *stopped)
+            .map(|(resource, used, limit, _)| LimitViolation {
+                resource,
+                used,
+                limit,
+            })
+            .collect()
+    }
+
+    /// Whether any resource exceeds the limits scaled for this run.
+    pub fn exceeds_limits(&self) -> bool {
+        !self.limit_violations().is_empty()
+    }
+
+    /// The outcome as an [`Error::Function`] when the Function didn't run to completion, as runs don't fail
+    /// because of the Function.
+    pub fn error(&self) -> Option<Error> {
+        match self.outcome {
+            FunctionOutcome::Success => None,
+            ref outcome => Some(Error::Function(outcome.clone())),
+        }
+    }
 }
 
 fn humanize_size(title: &str, size_bytes: u64, size_limit: u64) -> String {
@@ -93,13 +554,25 @@ fn humanize_instructions(title: &str, instructions: u64, instructions_limit: u64
 
 impl fmt::Display for FunctionRunResult {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let input = match &self.input {
+            FunctionInput::JsonInput(value) => serde_json::to_string_pretty(value)
+                .expect("Input should be serializable to a string"),
+            FunctionInput::BinaryInput(binary_input) => format!(
+                "{} bytes the {} codec couldn't decode:\n{}",
+                binary_input.bytes.len(),
+                self.input_codec,
+                hex_bytes(&binary_input.bytes)
+            ),
+        };
         writeln!(
             formatter,
             "{}\n\n{}",
             "            Input            ".black().on_bright_yellow(),
-            serde_json::to_string_pretty(&self.input)
-                .expect("Input should be serializable to a string")
+            input
         )?;
+        if self.input_codec != JsonCodec.name() {
+            writeln!(formatter, "{}", format!("({})", self.input_codec).dimmed())?;
+        }
 
         writeln!(
             formatter,
@@ -109,16 +582,52 @@ impl fmt::Display for FunctionRunResult {
         )?;
 
         let logs_length = self.logs.len();
-        if logs_length > FUNCTION_LOG_LIMIT {
+        let logs_limit = self.limits.logs;
+        if logs_length as u64 > logs_limit {
             writeln!(
                 formatter,
                 "{}\n\n",
                 &format!(
-                    "Logs would be truncated in production, length {logs_length} > {FUNCTION_LOG_LIMIT} limit",
+                    "Logs would be truncated in production, length {logs_length} > {logs_limit} limit",
                 ).red()
             )?;
         }
 
+        let outcome_title = "           Outcome           ".black();
+        let outcome_message = match &self.outcome {
+            FunctionOutcome::Success => "Success".to_string(),
+            FunctionOutcome::Exited { code } => format!("Exited with code {code}"),
+            FunctionOutcome::Trapped { kind, message } => format!("Trapped: {kind}\n\n{message}"),
+            FunctionOutcome::InstructionLimitExceeded { limit } => format!(
+                "Instruction limit exceeded, execution was stopped after {limit} instructions"
+            ),
+            FunctionOutcome::TimedOut { timeout_ms } => {
+                format!("Timed out, execution was interrupted after {timeout_ms}ms")
+            }
+            FunctionOutcome::MemoryLimitExceeded { limit } => format!(
+                "Memory limit exceeded, growing linear memory beyond {limit} bytes was denied"
+            ),
+        };
+        if self.outcome == FunctionOutcome::Success {
+            writeln!(
+                formatter,
+                "{}\n\n{}\n",
+                outcome_title.on_bright_green(),
+                outcome_message
+            )?;
+        } else {
+            writeln!(
+                formatter,
+                "{}\n\n{}\n",
+                outcome_title.on_bright_red(),
+                outcome_message.red()
+            )?;
+        }
+
+        for disallowed_import in &self.disallowed_imports {
+            writeln!(formatter, "{}: {disallowed_import}\n", "warning".yellow())?;
+        }
+
         match &self.output {
             FunctionOutput::JsonOutput(json_output) => {
                 writeln!(
@@ -128,6 +637,9 @@ impl fmt::Display for FunctionRunResult {
                     serde_json::to_string_pretty(&json_output)
                         .expect("Output should be serializable to a string")
                 )?;
+                if self.output_codec != JsonCodec.name() {
+                    writeln!(formatter, "{}", format!("({})", self.output_codec).dimmed())?;
+                }
             }
             FunctionOutput::InvalidJsonOutput(invalid_output) => {
                 writeln!(
@@ -137,18 +649,27 @@ impl fmt::Display for FunctionRunResult {
                     invalid_output.stdout
                 )?;
 
-                writeln!(
+                write!(
                     formatter,
                     "{}\n\n{}",
-                    "         JSON Error         ".black().on_bright_red(),
+                    format!(
+                        "{:^28}",
+                        format!("{} Error", self.output_codec.to_uppercase())
+                    )
+                    .black()
+                    .on_bright_red(),
                     invalid_output.error
                 )?;
+                match invalid_output.offset {
+                    Some(offset) => writeln!(formatter, " (at byte {offset})")?,
+                    None => writeln!(formatter)?,
+                }
             }
         }
 
-        let input_size_limit = self.scale_factor * DEFAULT_INPUT_SIZE_LIMIT as f64;
-        let output_size_limit = self.scale_factor * DEFAULT_OUTPUT_SIZE_LIMIT as f64;
-        let instructions_size_limit = self.scale_factor * DEFAULT_INSTRUCTIONS_LIMIT as f64;
+        let input_size_limit = self.limits.input_size;
+        let output_size_limit = self.limits.output_size;
+        let instructions_size_limit = self.limits.instructions;
 
         writeln!(
             formatter,
@@ -161,29 +682,21 @@ impl fmt::Display for FunctionRunResult {
         writeln!(
             formatter,
             "{}",
-            humanize_size(
-                "Input Size",
-                input_size_limit as u64,
-                input_size_limit as u64
-            )
+            humanize_size("Input Size", input_size_limit, input_size_limit)
         )?;
 
         writeln!(
             formatter,
             "{}",
-            humanize_size(
-                "Output Size",
-                output_size_limit as u64,
-                output_size_limit as u64
-            )
+            humanize_size("Output Size", output_size_limit, output_size_limit)
         )?;
         writeln!(
             formatter,
             "{}",
             humanize_instructions(
                 "Instructions",
-                instructions_size_limit as u64,
-                instructions_size_limit as u64
+                instructions_size_limit,
+                instructions_size_limit
             )
         )?;
 
@@ -194,32 +707,35 @@ impl fmt::Display for FunctionRunResult {
         write!(formatter, "\n\n{title}\n\n")?;
         writeln!(formatter, "Name: {}", self.name)?;
         writeln!(formatter, "Linear Memory Usage: {}KB", self.memory_usage)?;
+        for memory in &self.memories {
+            let peak = match memory.peak {
+                Some(peak) => format!("{}KB", peak / 1024),
+                None => "unknown (not exported)".to_string(),
+            };
+            writeln!(
+                formatter,
+                "  {} memory {}: initial {}KB, peak {}",
+                memory.instance,
+                memory.index,
+                memory.initial / 1024,
+                peak
+            )?;
+        }
+        writeln!(formatter, "Memory Grow Calls: {}", self.memory_grow_calls)?;
         writeln!(
             formatter,
             "{}",
-            humanize_instructions(
-                "Instructions",
-                self.instructions,
-                instructions_size_limit as u64
-            )
+            humanize_instructions("Instructions", self.instructions, instructions_size_limit)
         )?;
         writeln!(
             formatter,
             "{}",
-            humanize_size(
-                "Input Size",
-                self.input_size() as u64,
-                input_size_limit as u64,
-            )
+            humanize_size("Input Size", self.input_size() as u64, input_size_limit,)
         )?;
         writeln!(
             formatter,
             "{}",
-            humanize_size(
-                "Output Size",
-                self.output_size() as u64,
-                output_size_limit as u64,
-            )
+            humanize_size("Output Size", self.output_size() as u64, output_size_limit,)
         )?;
 
         writeln!(formatter, "Module Size: {}KB\n", self.size)?;
@@ -234,34 +750,83 @@ mod tests {
     use predicates::prelude::*;
 
     use super::*;
+    use crate::codec::MessagePackCodec;
+
+    /// A successful run with the input, for tests to override the fields they check.
+    fn test_result(input: FunctionInput) -> FunctionRunResult {
+        FunctionRunResult {
+            name: "test".to_string(),
+            size: 100,
+            memory_usage: 1000,
+            memories: vec![],
+            memory_grow_calls: 0,
+            instructions: 1000,
+            logs: String::new(),
+            input,
+            input_codec: default_codec(),
+            raw_input_size: None,
+            output: FunctionOutput::JsonOutput(serde_json::json!({})),
+            output_codec: default_codec(),
+            raw_output_size: None,
+            outcome: FunctionOutcome::Success,
+            scale_factor: 1.0,
+            limits: ResourceLimits::default(),
+            budget_used: BudgetUsage::default(),
+            violations: vec![],
+            disallowed_imports: vec![],
+            profile: None,
+        }
+    }
 
     #[test]
     fn test_js_output() -> Result<()> {
         let mock_input_string = "{\"input_test\": \"input_value\"}".to_string();
-        let mock_function_input = serde_json::from_str(&mock_input_string)?;
+        let mock_function_input: serde_json::Value = serde_json::from_str(&mock_input_string)?;
         let expected_input_display = serde_json::to_string_pretty(&mock_function_input)?;
 
         let function_run_result = FunctionRunResult {
-            name: "test".to_string(),
-            size: 100,
-            memory_usage: 1000,
+            memories: vec![
+                MemoryUsage {
+                    instance: "Function".to_string(),
+                    index: 0,
+                    initial: 64 * 1024,
+                    peak: Some(128 * 1024),
+                },
+                MemoryUsage {
+                    instance: "javy_quickjs_provider_v1".to_string(),
+                    index: 0,
+                    initial: 1024 * 1024,
+                    peak: None,
+                },
+            ],
+            memory_grow_calls: 4,
             instructions: 1001,
             logs: "test".to_string(),
-            input: mock_function_input,
             output: FunctionOutput::JsonOutput(serde_json::json!({
                 "test": "test"
             })),
-            profile: None,
-            scale_factor: 1.0,
+            ..test_result(mock_function_input.into())
         };
 
         let predicate = predicates::str::contains("Instructions: 1.001K")
             .and(predicates::str::contains("Linear Memory Usage: 1000KB"))
             .and(predicates::str::contains(expected_input_display))
             .and(predicates::str::contains("Input Size: 28B"))
-            .and(predicates::str::contains("Output Size: 15B"));
+            .and(predicates::str::contains("Output Size: 15B"))
+            .and(predicates::str::contains(
+                "Function memory 0: initial 64KB, peak 128KB",
+            ))
+            .and(predicates::str::contains(
+                "javy_quickjs_provider_v1 memory 0: initial 1024KB, peak unknown (not exported)",
+            ))
+            .and(predicates::str::contains("Memory Grow Calls: 4"));
         assert!(predicate.eval(&function_run_result.to_string()));
 
+        let json: serde_json::Value = serde_json::from_str(&function_run_result.to_json())?;
+        assert_eq!(json["memories"][1]["instance"], "javy_quickjs_provider_v1");
+        assert_eq!(json["memories"][1]["peak"], serde_json::Value::Null);
+        assert_eq!(json["memory_grow_calls"], 4);
+
         assert!(predicate.eval(&function_run_result.to_string()));
         Ok(())
     }
@@ -269,21 +834,15 @@ mod tests {
     #[test]
     fn test_js_output_1000() -> Result<()> {
         let mock_input_string = "{\"input_test\": \"input_value\"}".to_string();
-        let mock_function_input = serde_json::from_str(&mock_input_string)?;
+        let mock_function_input: serde_json::Value = serde_json::from_str(&mock_input_string)?;
         let expected_input_display = serde_json::to_string_pretty(&mock_function_input)?;
 
         let function_run_result = FunctionRunResult {
-            name: "test".to_string(),
-            size: 100,
-            memory_usage: 1000,
-            instructions: 1000,
             logs: "test".to_string(),
-            input: mock_function_input,
             output: FunctionOutput::JsonOutput(serde_json::json!({
                 "test": "test"
             })),
-            profile: None,
-            scale_factor: 1.0,
+            ..test_result(mock_function_input.into())
         };
 
         let predicate = predicates::str::contains("Instructions: 1")
@@ -296,21 +855,16 @@ mod tests {
     #[test]
     fn test_instructions_less_than_1000() -> Result<()> {
         let mock_input_string = "{\"input_test\": \"input_value\"}".to_string();
-        let mock_function_input = serde_json::from_str(&mock_input_string)?;
+        let mock_function_input: serde_json::Value = serde_json::from_str(&mock_input_string)?;
         let expected_input_display = serde_json::to_string_pretty(&mock_function_input)?;
 
         let function_run_result = FunctionRunResult {
-            name: "test".to_string(),
-            size: 100,
-            memory_usage: 1000,
             instructions: 999,
             logs: "test".to_string(),
-            input: mock_function_input,
             output: FunctionOutput::JsonOutput(serde_json::json!({
                 "test": "test"
             })),
-            profile: None,
-            scale_factor: 1.0,
+            ..test_result(mock_function_input.into())
         };
 
         let predicate = predicates::str::contains("Instructions: 999")
@@ -319,4 +873,198 @@ mod tests {
         assert!(predicate.eval(&function_run_result.to_string()));
         Ok(())
     }
+
+    #[test]
+    fn test_limit_violations() {
+        let mut function_run_result = FunctionRunResult {
+            instructions: 12_000_000,
+            logs: "a".repeat(FUNCTION_LOG_LIMIT + 1),
+            ..test_result(serde_json::json!({}).into())
+        };
+        function_run_result.apply_limits(LimitProfile::default().scaled(1.0));
+
+        assert_eq!(
+            function_run_result.violations,
+            [
+                LimitViolation {
+                    resource: LimitedResource::Instructions,
+                    used: 12_000_000,
+                    limit: DEFAULT_INSTRUCTIONS_LIMIT,
+                },
+                LimitViolation {
+                    resource: LimitedResource::Logs,
+                    used: 1001,
+                    limit: 1000,
+                },
+            ]
+        );
+        assert_eq!(
+            function_run_result.violations[1].to_string(),
+            "logs length exceeded: used 1001, limit is 1000"
+        );
+
+        let mut scaled = FunctionRunResult {
+            scale_factor: 1.2,
+            logs: String::new(),
+            ..function_run_result.clone()
+        };
+        scaled.apply_limits(LimitProfile::default().scaled(1.2));
+        assert!(!scaled.exceeds_limits());
+
+        let stopped = FunctionRunResult {
+            outcome: FunctionOutcome::MemoryLimitExceeded { limit: 65536 },
+            ..scaled
+        };
+        assert_eq!(
+            stopped.limit_violations(),
+            [LimitViolation {
+                resource: LimitedResource::Memory,
+                used: 1000 * 1024,
+                limit: 65536,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_limits_and_budget_in_json() -> Result<()> {
+        let mut function_run_result = FunctionRunResult {
+            memory_usage: 2500,
+            instructions: 5_500_000,
+            logs: "a".repeat(250),
+            scale_factor: 2.0,
+            ..test_result(serde_json::json!({"a": "b"}).into())
+        };
+        function_run_result.apply_limits(LimitProfile::default().scaled(2.0));
+
+        let json: serde_json::Value = serde_json::from_str(&function_run_result.to_json())?;
+        assert_eq!(json["scale_factor"], 2.0);
+        assert_eq!(
+            json["limits"],
+            serde_json::json!({
+                "instructions": 22_000_000,
+                "input_size": 128_000,
+                "output_size": 40_000,
+                "logs": 1000,
+                "memory": 10_000_000
+            })
+        );
+        assert_eq!(
+            json["budget_used"],
+            serde_json::json!({
+                "instructions": 25.0,
+                "input_size": 0.01,
+                "output_size": 0.01,
+                "logs": 25.0,
+                "memory": 25.6
+            })
+        );
+        assert_eq!(json["violations"], serde_json::json!([]));
+
+        let deserialized: FunctionRunResult = serde_json::from_value(json)?;
+        assert_eq!(deserialized.limits, function_run_result.limits);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_outcome_in_json_and_display() -> Result<()> {
+        let function_run_result = FunctionRunResult {
+            logs: "printed by the Function".to_string(),
+            outcome: FunctionOutcome::Trapped {
+                kind: TrapKind::UnreachableCodeReached,
+                message: "error while executing at wasm backtrace".to_string(),
+            },
+            ..test_result(serde_json::json!({}).into())
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&function_run_result.to_json())?;
+        assert_eq!(
+            json["outcome"],
+            serde_json::json!({
+                "status": "trapped",
+                "kind": "unreachable_code_reached",
+                "message": "error while executing at wasm backtrace"
+            })
+        );
+        assert_eq!(json["logs"], "printed by the Function");
+
+        let display = function_run_result.to_string();
+        assert!(display.contains("Outcome"));
+        assert!(display.contains("Trapped: unreachable instruction executed"));
+
+        let exited = FunctionRunResult {
+            outcome: FunctionOutcome::Exited { code: 2 },
+            ..function_run_result
+        };
+        assert_eq!(
+            serde_json::to_value(&exited.outcome)?,
+            serde_json::json!({"status": "exited", "code": 2})
+        );
+        assert!(exited.to_string().contains("Exited with code 2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_output() {
+        let bytes = rmp_serde::to_vec(&serde_json::json!({"a": [1, "b"]})).unwrap();
+        let FunctionOutput::JsonOutput(value) = FunctionOutput::decode(&bytes, &MessagePackCodec)
+        else {
+            panic!("Expected MessagePack output to decode");
+        };
+        assert_eq!(value, serde_json::json!({"a": [1, "b"]}));
+
+        let FunctionOutput::InvalidJsonOutput(invalid_output) =
+            FunctionOutput::decode(b"{\n  \"a\": 1,\n}", &JsonCodec)
+        else {
+            panic!("Expected invalid output");
+        };
+        assert_eq!(invalid_output.offset, Some(12));
+        assert_eq!(invalid_output.stdout, "{\n  \"a\": 1,\n}");
+
+        let FunctionOutput::InvalidJsonOutput(invalid_output) =
+            FunctionOutput::decode(&bytes[..bytes.len() - 1], &MessagePackCodec)
+        else {
+            panic!("Expected invalid output");
+        };
+        assert_eq!(invalid_output.offset, Some(bytes.len() - 1));
+        assert_eq!(invalid_output.stdout, "81 a1 61 92 01 a1");
+    }
+
+    #[test]
+    fn test_binary_input() -> Result<()> {
+        let input = FunctionInput::decode(&[0x81, 0xa1, 0x61, 0x01], &JsonCodec);
+        assert_eq!(
+            input,
+            FunctionInput::BinaryInput(BinaryInput {
+                bytes: vec![0x81, 0xa1, 0x61, 0x01]
+            })
+        );
+
+        let function_run_result = test_result(input);
+        assert_eq!(function_run_result.input_size(), 4);
+        assert!(function_run_result
+            .to_string()
+            .contains("4 bytes the json codec couldn't decode:\n81 a1 61 01"));
+
+        let json: serde_json::Value = serde_json::from_str(&function_run_result.to_json())?;
+        assert_eq!(json["input"], serde_json::Value::Null);
+        assert_eq!(json["raw_input"], serde_json::json!({"base64": "gaFhAQ=="}));
+        let deserialized: FunctionRunResult = serde_json::from_value(json)?;
+        assert_eq!(deserialized.input, function_run_result.input);
+
+        // JSON input shaped like binary input stays JSON
+        let json_input: FunctionInput = serde_json::json!({"base64": "gaFhAQ=="}).into();
+        let function_run_result = FunctionRunResult {
+            input: json_input.clone(),
+            ..function_run_result
+        };
+        let json: serde_json::Value = serde_json::from_str(&function_run_result.to_json())?;
+        assert_eq!(json["input"], serde_json::json!({"base64": "gaFhAQ=="}));
+        assert!(json.get("raw_input").is_none());
+        let deserialized: FunctionRunResult = serde_json::from_value(json)?;
+        assert_eq!(deserialized.input, json_input);
+
+        Ok(())
+    }
 }
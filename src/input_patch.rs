@@ -0,0 +1,369 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{path::Path, str::FromStr};
+
+/// A `path=value` override of one value of the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputOverride {
+    /// JSON pointer to the value.
+    pub pointer: String,
+    pub value: Value,
+}
+
+impl FromStr for InputOverride {
+    type Err = anyhow::Error;
+
+    /// Parse `path=value`, the path being a JSON pointer such as `/cart/lines/0/quantity` or a dotted path such as
+    /// `cart.lines.0.quantity`, and the value JSON or, when it isn't valid JSON, a string.
+    fn from_str(input: &str) -> Result<Self> {
+        let (path, value) = input
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid override {:?}, expected path=value", input))?;
+
+        Ok(Self {
+            pointer: to_pointer(path),
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string())),
+        })
+    }
+}
+
+impl InputOverride {
+    pub fn apply(&self, input: &mut Value) -> Result<()> {
+        set(input, &self.pointer, self.value.clone())
+    }
+}
+
+/// A JSON pointer for a path that is either already a pointer or dotted.
+pub fn to_pointer(path: &str) -> String {
+    if path.is_empty() || path.starts_with('/') {
+        return path.to_string();
+    }
+
+    path.split('.')
+        .flat_map(|token| ["/".to_string(), token.replace('~', "~0").replace('/', "~1")])
+        .collect()
+}
+
+fn tokens(pointer: &str) -> Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let Some(pointer) = pointer.strip_prefix('/') else {
+        return Err(anyhow!(
+            "Invalid JSON pointer {:?}, expected a leading /",
+            pointer
+        ));
+    };
+
+    Ok(pointer
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn array_index(token: &str, len: usize, pointer: &str) -> Result<usize> {
+    match token {
+        "-" => Ok(len),
+        token => token
+            .parse()
+            .ok()
+            .filter(|index| *index <= len)
+            .ok_or_else(|| anyhow!("Invalid array index {:?} in {:?}", token, pointer)),
+    }
+}
+
+/// The container holding the value `pointer` refers to, along with the last token of the pointer. Missing objects
+/// on the way are created when `create` is set.
+fn parent_mut<'a>(
+    input: &'a mut Value,
+    pointer: &str,
+    create: bool,
+) -> Result<(&'a mut Value, String)> {
+    let mut tokens = tokens(pointer)?;
+    let last = tokens
+        .pop()
+        .ok_or_else(|| anyhow!("The whole input can't be the target of {:?}", pointer))?;
+
+    let mut current = input;
+    for token in tokens {
+        current = match current {
+            Value::Object(map) => {
+                if create {
+                    map.entry(token)
+                        .or_insert_with(|| Value::Object(Default::default()))
+                } else {
+                    map.get_mut(&token)
+                        .ok_or_else(|| anyhow!("No value at {:?}", pointer))?
+                }
+            }
+            Value::Array(array) => {
+                let index = array_index(&token, array.len(), pointer)?;
+                array
+                    .get_mut(index)
+                    .ok_or_else(|| anyhow!("No value at {:?}", pointer))?
+            }
+            _ => {
+                return Err(anyhow!(
+                    "{:?} goes through a value that isn't an object or array",
+                    pointer
+                ))
+            }
+        };
+    }
+
+    Ok((current, last))
+}
+
+/// Set the value at `pointer`, replacing it or adding it along with any missing parent objects.
+pub fn set(input: &mut Value, pointer: &str, value: Value) -> Result<()> {
+    if pointer.is_empty() {
+        *input = value;
+        return Ok(());
+    }
+
+    match parent_mut(input, pointer, true)? {
+        (Value::Object(map), key) => {
+            map.insert(key, value);
+        }
+        (Value::Array(array), token) => match array_index(&token, array.len(), pointer)? {
+            index if index == array.len() => array.push(value),
+            index => array[index] = value,
+        },
+        _ => return Err(anyhow!("{:?} isn't in an object or array", pointer)),
+    }
+
+    Ok(())
+}
+
+fn add(input: &mut Value, pointer: &str, value: Value) -> Result<()> {
+    if pointer.is_empty() {
+        *input = value;
+        return Ok(());
+    }
+
+    match parent_mut(input, pointer, false)? {
+        (Value::Object(map), key) => {
+            map.insert(key, value);
+        }
+        (Value::Array(array), token) => {
+            let index = array_index(&token, array.len(), pointer)?;
+            array.insert(index, value);
+        }
+        _ => return Err(anyhow!("{:?} isn't in an object or array", pointer)),
+    }
+
+    Ok(())
+}
+
+fn remove(input: &mut Value, pointer: &str) -> Result<Value> {
+    let removed = match parent_mut(input, pointer, false)? {
+        (Value::Object(map), key) => map.remove(&key),
+        (Value::Array(array), token) => match array_index(&token, array.len(), pointer)? {
+            index if index < array.len() => Some(array.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    removed.ok_or_else(|| anyhow!("No value at {:?}", pointer))
+}
+
+fn get<'a>(input: &'a Value, pointer: &str) -> Result<&'a Value> {
+    input
+        .pointer(pointer)
+        .ok_or_else(|| anyhow!("No value at {:?}", pointer))
+}
+
+/// An operation of a JSON Patch, see RFC 6902.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl PatchOperation {
+    fn apply(&self, input: &mut Value) -> Result<()> {
+        match self {
+            PatchOperation::Add { path, value } => add(input, path, value.clone()),
+            PatchOperation::Remove { path } => remove(input, path).map(drop),
+            PatchOperation::Replace { path, value } => {
+                *input
+                    .pointer_mut(path)
+                    .ok_or_else(|| anyhow!("No value at {:?}", path))? = value.clone();
+                Ok(())
+            }
+            PatchOperation::Move { from, path } => {
+                if path.starts_with(&format!("{from}/")) {
+                    return Err(anyhow!("Can't move {:?} into one of its children", from));
+                }
+                let value = remove(input, from)?;
+                add(input, path, value)
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = get(input, from)?.clone();
+                add(input, path, value)
+            }
+            PatchOperation::Test { path, value } => match get(input, path)? {
+                actual if actual == value => Ok(()),
+                actual => Err(anyhow!(
+                    "Test failed, {:?} is {} instead of {}",
+                    path,
+                    actual,
+                    value
+                )),
+            },
+        }
+    }
+}
+
+/// A patch applied to the input: a JSON Patch (RFC 6902) when it is an array, a JSON merge patch (RFC 7386)
+/// otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Patch {
+    Json(Vec<PatchOperation>),
+    Merge(Value),
+}
+
+impl Patch {
+    pub fn parse(contents: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(contents)?;
+
+        Ok(match value {
+            Value::Array(_) => Patch::Json(serde_json::from_value(value)?),
+            value => Patch::Merge(value),
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Couldn't load patch {:?}: {}", path, e))?;
+
+        Self::parse(&contents).map_err(|e| anyhow!("Invalid patch {:?}: {}", path, e))
+    }
+
+    /// Apply the whole patch, or leave the input as it was when any operation fails.
+    pub fn apply(&self, input: &mut Value) -> Result<()> {
+        match self {
+            Patch::Json(operations) => {
+                let mut patched = input.clone();
+                for (index, operation) in operations.iter().enumerate() {
+                    operation
+                        .apply(&mut patched)
+                        .map_err(|e| anyhow!("Patch operation {} failed: {}", index, e))?;
+                }
+                *input = patched;
+            }
+            Patch::Merge(patch) => merge(input, patch),
+        }
+
+        Ok(())
+    }
+}
+
+fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    let mut object = match std::mem::take(target) {
+        Value::Object(object) => object,
+        _ => Default::default(),
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            object.remove(key);
+        } else {
+            merge(object.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+    *target = Value::Object(object);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_overrides() -> Result<()> {
+        let mut input = json!({"cart": {"lines": [{"quantity": 1}, {"quantity": 2}]}});
+
+        for override_ in [
+            "cart.lines.0.quantity=5",
+            "/cart/lines/1/quantity=null",
+            "cart.buyerIdentity.email=someone@example.com",
+            "/cart/lines/-={\"quantity\": 3}",
+        ] {
+            InputOverride::from_str(override_)?.apply(&mut input)?;
+        }
+        assert_eq!(
+            input,
+            json!({"cart": {
+                "lines": [{"quantity": 5}, {"quantity": null}, {"quantity": 3}],
+                "buyerIdentity": {"email": "someone@example.com"},
+            }})
+        );
+
+        assert_eq!(to_pointer("a/b.c~d"), "/a~1b/c~0d");
+        assert!(InputOverride::from_str("cart.lines").is_err());
+        assert!(InputOverride::from_str("cart.lines.7=1")?
+            .apply(&mut input)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_patch() -> Result<()> {
+        let mut input = json!({"a": [1, 2], "b": {"c": "d"}});
+        Patch::parse(
+            r#"[
+                {"op": "test", "path": "/b/c", "value": "d"},
+                {"op": "add", "path": "/a/1", "value": 3},
+                {"op": "remove", "path": "/a/0"},
+                {"op": "replace", "path": "/b/c", "value": "e"},
+                {"op": "copy", "from": "/b", "path": "/f"},
+                {"op": "move", "from": "/a", "path": "/b/a"}
+            ]"#,
+        )?
+        .apply(&mut input)?;
+        assert_eq!(
+            input,
+            json!({"b": {"c": "e", "a": [3, 2]}, "f": {"c": "e"}})
+        );
+
+        let error = Patch::parse(
+            r#"[
+                {"op": "remove", "path": "/f"},
+                {"op": "test", "path": "/b/c", "value": "d"}
+            ]"#,
+        )?
+        .apply(&mut input)
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Patch operation 1 failed: Test failed, \"/b/c\" is \"e\" instead of \"d\""
+        );
+        assert_eq!(input["f"], json!({"c": "e"}));
+
+        assert!(Patch::parse(r#"[{"op": "rename", "path": "/a"}]"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_patch() -> Result<()> {
+        let mut input = json!({"a": "b", "c": {"d": "e", "f": "g"}});
+        Patch::parse(r#"{"a": "z", "c": {"f": null}, "h": [1]}"#)?.apply(&mut input)?;
+        assert_eq!(input, json!({"a": "z", "c": {"d": "e"}, "h": [1]}));
+
+        Ok(())
+    }
+}
@@ -1,5 +1,16 @@
+pub mod batch;
 pub mod bluejay_schema_analyzer;
+pub mod codec;
 pub mod engine;
+mod epoch_ticker;
+pub mod error;
+pub mod fixture_tests;
 pub mod function_run_result;
+pub mod input_patch;
+pub mod limit_profile;
 pub mod logs;
+pub mod module_cache;
+pub mod module_check;
+pub mod module_info;
+pub mod project_config;
 pub mod scale_limits_analyzer;
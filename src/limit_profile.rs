@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{
+    engine::is_standard_provider,
+    function_run_result::{
+        ResourceLimits, DEFAULT_INPUT_SIZE_LIMIT, DEFAULT_INSTRUCTIONS_LIMIT,
+        DEFAULT_LINEAR_MEMORY_LIMIT, DEFAULT_OUTPUT_SIZE_LIMIT, FUNCTION_LOG_LIMIT,
+    },
+};
+
+pub const DEFAULT_MIN_SCALE_FACTOR: f64 = 1.0;
+pub const DEFAULT_MAX_SCALE_FACTOR: f64 = 10.0;
+/// Largest module accepted on upload, in bytes.
+pub const DEFAULT_MODULE_SIZE_LIMIT: u64 = 256_000;
+
+pub const WASI_MODULE_NAME: &str = "wasi_snapshot_preview1";
+
+/// WASI functions Functions are known to import: those imported by the embedded Javy providers and by the Rust
+/// fixtures in `tests/fixtures` (listed by `function-runner inspect`), plus `args_get`, `args_sizes_get`,
+/// `fd_prestat_get` and `fd_prestat_dir_name` that wasi-libc's startup code calls (`__main_void.c` and
+/// `preopens.c`). This isn't the production allowlist, which isn't published, so other imports are only reported
+/// unless the limit profile sets `enforce_imports`.
+pub const ALLOWED_WASI_FUNCTIONS: [&str; 14] = [
+    "args_get",
+    "args_sizes_get",
+    "clock_time_get",
+    "environ_get",
+    "environ_sizes_get",
+    "fd_close",
+    "fd_fdstat_get",
+    "fd_prestat_dir_name",
+    "fd_prestat_get",
+    "fd_read",
+    "fd_seek",
+    "fd_write",
+    "proc_exit",
+    "random_get",
+];
+
+/// Names of the built-in profiles accepted by [`LimitProfile::preset`]. They aren't specific to a Function API or
+/// API version, limits that differ for an API are loaded from a profile file.
+pub const PRESET_NAMES: [&str; 2] = ["default", "unscaled"];
+
+/// The limits a Function is judged against before scaling, along with the bounds of its scale factor.
+///
+/// Profiles are loaded from TOML or JSON files, fields that are omitted keep the production defaults:
+///
+/// ```toml
+/// instructions = 11000000
+/// input_size = 64000
+/// output_size = 20000
+/// logs = 1000
+/// memory = 10000000
+/// module_size = 256000
+/// allowed_imports = ["wasi_snapshot_preview1::fd_write", "env::*"]
+/// enforce_imports = true
+/// min_scale_factor = 1.0
+/// max_scale_factor = 10.0
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitProfile {
+    pub instructions: u64,
+    /// In bytes.
+    pub input_size: u64,
+    /// In bytes.
+    pub output_size: u64,
+    /// Length of the logs in bytes.
+    pub logs: u64,
+    /// Size of any linear memory in bytes.
+    pub memory: u64,
+    /// Size of the uploaded module in bytes, it isn't scaled.
+    pub module_size: u64,
+    /// Imports the Function may use, as `module::name` or `module::*` for every item of a module. Imports from
+    /// the embedded providers are always allowed.
+    pub allowed_imports: Vec<String>,
+    /// Refuse to run Functions importing anything `allowed_imports` doesn't allow, otherwise they run and the
+    /// disallowed imports are reported.
+    pub enforce_imports: bool,
+    pub min_scale_factor: f64,
+    pub max_scale_factor: f64,
+}
+
+impl Default for LimitProfile {
+    fn default() -> Self {
+        Self {
+            instructions: DEFAULT_INSTRUCTIONS_LIMIT,
+            input_size: DEFAULT_INPUT_SIZE_LIMIT,
+            output_size: DEFAULT_OUTPUT_SIZE_LIMIT,
+            logs: FUNCTION_LOG_LIMIT as u64,
+            memory: DEFAULT_LINEAR_MEMORY_LIMIT,
+            module_size: DEFAULT_MODULE_SIZE_LIMIT,
+            allowed_imports: ALLOWED_WASI_FUNCTIONS
+                .iter()
+                .map(|name| format!("{WASI_MODULE_NAME}::{name}"))
+                .collect(),
+            enforce_imports: false,
+            min_scale_factor: DEFAULT_MIN_SCALE_FACTOR,
+            max_scale_factor: DEFAULT_MAX_SCALE_FACTOR,
+        }
+    }
+}
+
+impl LimitProfile {
+    /// A built-in profile: `default` has the production limits, `unscaled` the same limits without scaling
+    /// them for large inputs.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "unscaled" => Some(Self {
+                max_scale_factor: DEFAULT_MIN_SCALE_FACTOR,
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Read a profile from a `.toml` file, or from a JSON file for any other extension.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Couldn't load limit profile {:?}: {}", path, e))?;
+
+        let profile: Self = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&contents)
+                .map_err(|e| anyhow!("Invalid limit profile {:?}: {}", path, e))?
+        } else {
+            serde_json::from_str(&contents)
+                .map_err(|e| anyhow!("Invalid limit profile {:?}: {}", path, e))?
+        };
+
+        if profile.min_scale_factor > profile.max_scale_factor {
+            return Err(anyhow!(
+                "Invalid limit profile {:?}: min_scale_factor {} is greater than max_scale_factor {}",
+                path,
+                profile.min_scale_factor,
+                profile.max_scale_factor
+            ));
+        }
+
+        if let Some(allowed_import) = profile
+            .allowed_imports
+            .iter()
+            .find(|allowed_import| !allowed_import.contains("::"))
+        {
+            return Err(anyhow!(
+                "Invalid limit profile {:?}: allowed import {:?} should be module::name or module::*",
+                path,
+                allowed_import
+            ));
+        }
+
+        Ok(profile)
+    }
+
+    /// The preset with this name, otherwise the profile in the file at this path.
+    pub fn load(name_or_path: &str) -> Result<Self> {
+        if let Some(profile) = Self::preset(name_or_path) {
+            return Ok(profile);
+        }
+
+        let path = Path::new(name_or_path);
+        if !path.exists() {
+            return Err(anyhow!(
+                "Unknown limit profile {:?}, expected one of {} or the path to a profile file",
+                name_or_path,
+                PRESET_NAMES.join(", ")
+            ));
+        }
+
+        Self::from_file(path)
+    }
+
+    /// Whether the Function may import `name` from `module`.
+    pub fn allows_import(&self, module: &str, name: &str) -> bool {
+        is_standard_provider(module)
+            || self.allowed_imports.iter().any(|allowed_import| {
+                allowed_import
+                    .split_once("::")
+                    .is_some_and(|(allowed_module, allowed_name)| {
+                        allowed_module == module && (allowed_name == "*" || allowed_name == name)
+                    })
+            })
+    }
+
+    pub fn clamp_scale_factor(&self, scale_factor: f64) -> f64 {
+        scale_factor.clamp(self.min_scale_factor, self.max_scale_factor)
+    }
+
+    /// The limits for a run, with the instruction, input and output limits multiplied by the scale factor.
+    pub fn scaled(&self, scale_factor: f64) -> ResourceLimits {
+        ResourceLimits {
+            instructions: (scale_factor * self.instructions as f64) as u64,
+            input_size: (scale_factor * self.input_size as f64) as u64,
+            output_size: (scale_factor * self.output_size as f64) as u64,
+            logs: self.logs,
+            memory: self.memory,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_preset_and_files() -> Result<()> {
+        assert_eq!(LimitProfile::load("default")?, LimitProfile::default());
+        assert_eq!(LimitProfile::load("unscaled")?.clamp_scale_factor(3.0), 1.0);
+
+        let temp = assert_fs::TempDir::new()?;
+        let toml_path = temp.path().join("cart_transform.toml");
+        std::fs::write(
+            &toml_path,
+            "instructions = 5_000_000\nmax_scale_factor = 2.0\n",
+        )?;
+        let profile = LimitProfile::load(toml_path.to_str().unwrap())?;
+        assert_eq!(profile.instructions, 5_000_000);
+        assert_eq!(profile.output_size, DEFAULT_OUTPUT_SIZE_LIMIT);
+        assert_eq!(profile.clamp_scale_factor(3.0), 2.0);
+        assert_eq!(profile.scaled(2.0).instructions, 10_000_000);
+
+        let json_path = temp.path().join("discounts.json");
+        std::fs::write(&json_path, r#"{"output_size": 30000, "logs": 500}"#)?;
+        let profile = LimitProfile::from_file(&json_path)?;
+        assert_eq!(profile.output_size, 30_000);
+        assert_eq!(profile.logs, 500);
+
+        assert!(profile.allows_import(WASI_MODULE_NAME, "fd_write"));
+        assert!(profile.allows_import("javy_quickjs_provider_v1", "eval_bytecode"));
+        assert!(!profile.allows_import(WASI_MODULE_NAME, "path_open"));
+
+        std::fs::write(
+            &toml_path,
+            "allowed_imports = [\"wasi_snapshot_preview1::fd_write\", \"env::*\"]\n",
+        )?;
+        let profile = LimitProfile::from_file(&toml_path)?;
+        assert!(profile.allows_import("env", "abort"));
+        assert!(!profile.allows_import(WASI_MODULE_NAME, "proc_exit"));
+
+        std::fs::write(&toml_path, "allowed_imports = [\"fd_write\"]\n")?;
+        assert!(LimitProfile::from_file(&toml_path).is_err());
+
+        std::fs::write(&json_path, r#"{"output": 30000}"#)?;
+        assert!(LimitProfile::from_file(&json_path)
+            .unwrap_err()
+            .to_string()
+            .contains("unknown field `output`"));
+
+        let error = LimitProfile::load("payments").unwrap_err().to_string();
+        assert!(
+            error.contains("expected one of default, unscaled"),
+            "{error}"
+        );
+
+        Ok(())
+    }
+}
@@ -1,20 +1,47 @@
 use std::{
+    collections::{hash_map::Entry, HashMap},
     fs::File,
     io::{stdin, BufReader, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Result};
-use clap::{Parser, ValueEnum};
+use clap::{
+    parser::ValueSource, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
+};
 use function_runner::{
+    batch::{run_batch, summary_table, BatchCase, BatchEntry},
     bluejay_schema_analyzer::BluejaySchemaAnalyzer,
-    engine::{run, FunctionRunParams, ProfileOpts},
+    codec::{self, AutoCodec, EncodedInput, JsonCodec, MessagePackCodec, RawCodec},
+    engine::{self, FunctionRunner, ProfileOpts, RunOptions},
+    error::Error,
+    fixture_tests::{self, FixtureResult},
+    function_run_result::FunctionRunResult,
+    input_patch::{InputOverride, Patch},
+    limit_profile::LimitProfile,
+    module_cache::ModuleCache,
+    module_check,
+    module_info::ModuleInfo,
+    project_config::{self, ProjectConfig},
 };
 
+use colored::Colorize;
 use is_terminal::IsTerminal;
 
 const PROFILE_DEFAULT_INTERVAL: u32 = 500_000; // every 5us
 const DEFAULT_SCALE_FACTOR: f64 = 1.0;
+/// Exit code used by `--enforce-limits` when the Function exceeds a resource limit.
+const LIMITS_EXCEEDED_EXIT_CODE: u8 = 3;
+
+/// Whether the module cache was opened already by this process.
+static MODULE_CACHE_OPENED: AtomicBool = AtomicBool::new(false);
 
 /// Supported input flavors
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -25,29 +52,63 @@ enum Codec {
     Raw,
     /// JSON input, will be converted to MessagePack, must be valid JSON
     JsonToMessagepack,
+    /// JSON input, converted to MessagePack when the module hints it reads MessagePack, must be valid JSON
+    Auto,
+}
+
+impl Codec {
+    /// The output codec used when none is given: Functions taking MessagePack reply in MessagePack.
+    fn default_output_codec(self) -> OutputCodec {
+        match self {
+            Codec::Json | Codec::Raw => OutputCodec::Json,
+            Codec::JsonToMessagepack => OutputCodec::Messagepack,
+            Codec::Auto => OutputCodec::Auto,
+        }
+    }
+}
+
+/// Supported output flavors
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum OutputCodec {
+    /// JSON output
+    Json,
+    /// MessagePack output, shown as JSON
+    Messagepack,
+    /// JSON or MessagePack, detected from the output
+    Auto,
 }
 
 /// Simple Function runner which takes JSON as a convenience.
 #[derive(Parser, Debug)]
 #[clap(version)]
-#[command(arg_required_else_help = true)]
+#[command(arg_required_else_help = true, args_conflicts_with_subcommands = true)]
 struct Opts {
-    /// Path to wasm/wat Function
-    #[clap(short, long, default_value = "function.wasm")]
-    function: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    run_opts: RunOpts,
+}
+
+/// Options for a single run of the Function.
+#[derive(Args, Debug)]
+struct RunOpts {
+    #[clap(flatten)]
+    function_opts: FunctionOpts,
 
     /// Path to json file containing Function input; if omitted, stdin is used
     #[clap(short, long)]
     input: Option<PathBuf>,
 
-    /// Name of the export to invoke.
-    #[clap(short, long, default_value = "_start")]
-    export: String,
-
     /// Log the run result as a JSON object
     #[clap(short, long)]
     json: bool,
 
+    /// Exit with code 3 when the Function exceeds any of the production resource limits, or imports what the limit
+    /// profile doesn't allow
+    #[clap(long)]
+    enforce_limits: bool,
+
     /// Enable profiling. This will make your Function run slower.
     /// The resulting profile can be used in speedscope (https://www.speedscope.app/)
     /// Specifying --profile-* argument will also enable profiling.
@@ -61,10 +122,63 @@ struct Opts {
     /// How many samples per seconds. Defaults to 500_000 (every 5us).
     #[clap(long)]
     profile_frequency: Option<u32>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the Function once, the same as omitting the subcommand.
+    Run(RunOpts),
+    /// Run the Function against many inputs, compiling it only once.
+    Batch(BatchOpts),
+    /// Run every target from the config file against its own input, compiling the Function only once.
+    Targets(TargetsOpts),
+    /// Run the Function against every case in a directory and compare its output to the expected output.
+    Test(TestOpts),
+    /// List the exports, imports, memories and custom sections of the Function's module.
+    Inspect(InspectOpts),
+    /// Statically check the Function's module against the production constraints without running it.
+    Check(CheckOpts),
+}
+
+/// Options describing the Function and how its input is prepared and run.
+#[derive(Args, Clone, Debug)]
+struct FunctionOpts {
+    /// Path to a function-runner.toml or shopify.extension.toml; if omitted, one in the current directory is used
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Target from the config file whose export, query, codec and limits to use
+    #[clap(short, long)]
+    target: Option<String>,
+
+    /// Path to wasm/wat Function
+    #[clap(short, long, default_value = "function.wasm")]
+    function: PathBuf,
+
+    /// Name of the export to invoke.
+    #[clap(short, long, default_value = "_start")]
+    export: String,
 
     #[clap(short = 'c', long, value_enum, default_value = "json")]
     codec: Codec,
 
+    /// How to decode the Function's output. Defaults to messagepack with the json-to-messagepack codec, json otherwise
+    #[clap(long, value_enum)]
+    output_codec: Option<OutputCodec>,
+
+    /// Set a value of the JSON input, as path=value with a JSON pointer or a dotted path and a JSON value (anything
+    /// else is a string). Applied after the patches, can be repeated
+    #[clap(long = "set", value_name = "PATH=VALUE")]
+    overrides: Vec<InputOverride>,
+
+    /// Apply a JSON Patch (RFC 6902) or JSON merge patch (RFC 7386) file to the JSON input, can be repeated
+    #[clap(long = "patch", value_name = "FILE", value_parser = load_patch)]
+    patches: Vec<Patch>,
+
+    /// Codec detecting encodings, read from the module the first time it is needed.
+    #[clap(skip)]
+    auto_codec: OnceLock<AutoCodec>,
+
     /// Path to graphql file containing Function schema; if omitted, defaults will be used to calculate limits.
     #[clap(short = 's', long)]
     schema_path: Option<PathBuf>,
@@ -72,9 +186,121 @@ struct Opts {
     /// Path to graphql file containing Function input query; if omitted, defaults will be used to calculate limits.
     #[clap(short = 'q', long)]
     query_path: Option<PathBuf>,
+
+    /// Stop the Function once it exceeds the scaled instruction limit, as production does.
+    #[clap(long)]
+    enforce_instructions_limit: bool,
+
+    /// Interrupt the Function after it has been running for this many milliseconds. Can't be used with profiling.
+    #[clap(long, value_name = "MILLISECONDS")]
+    timeout: Option<u64>,
+
+    /// Deny growing any linear memory beyond this many bytes. Defaults to the memory limit of the limit profile.
+    #[clap(long, value_name = "BYTES")]
+    memory_limit: Option<u64>,
+
+    /// Limits to judge the Function against: a built-in profile (default, unscaled) or the path to a TOML or JSON
+    /// limit profile.
+    #[clap(long = "limits", value_name = "PROFILE", value_parser = LimitProfile::load, default_value = "default")]
+    limit_profile: LimitProfile,
+
+    /// Directory where compiled modules are cached, only writable by the current user. Defaults to
+    /// `function-runner` in the user's cache directory, e.g. `~/.cache`, which is skipped with a warning when it
+    /// isn't private.
+    #[clap(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Compile the Function and providers on every run instead of using the compiled module cache.
+    #[clap(long, conflicts_with = "cache_dir")]
+    no_cache: bool,
+
+    /// Remove every compiled module from the cache before running. Entries are otherwise kept until removed.
+    #[clap(long, conflicts_with = "no_cache")]
+    clear_cache: bool,
+}
+
+#[derive(Args, Debug)]
+struct BatchOpts {
+    #[clap(flatten)]
+    function_opts: FunctionOpts,
+
+    /// Paths to input files, or to directories whose `.json` files are used as inputs
+    #[clap(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// How many inputs to run in parallel.
+    #[clap(short, long, default_value_t = 1)]
+    jobs: usize,
+
+    /// Print one JSON object per input instead of a summary table
+    #[clap(long)]
+    ndjson: bool,
+}
+
+#[derive(Args, Debug)]
+struct TargetsOpts {
+    #[clap(flatten)]
+    function_opts: FunctionOpts,
+
+    /// Input for a target as TARGET=PATH, overriding the `input` from the config
+    #[clap(short, long = "input", value_name = "TARGET=PATH", value_parser = parse_target_input)]
+    inputs: Vec<(String, PathBuf)>,
+
+    /// Print one JSON object per target instead of a summary table
+    #[clap(long)]
+    ndjson: bool,
+}
+
+fn parse_target_input(value: &str) -> Result<(String, PathBuf)> {
+    value
+        .split_once('=')
+        .map(|(target, path)| (target.to_string(), PathBuf::from(path)))
+        .ok_or_else(|| anyhow!("expected TARGET=PATH"))
+}
+
+#[derive(Args, Debug)]
+struct TestOpts {
+    #[clap(flatten)]
+    function_opts: FunctionOpts,
+
+    /// Directory containing one sub-directory per case, each with an `input.json` and an `expected.json`
+    #[clap(default_value = "cases")]
+    cases_dir: PathBuf,
+
+    /// Overwrite each case's `expected.json` with the Function's actual output
+    #[clap(long)]
+    update: bool,
+
+    /// Write a JUnit XML report to this path
+    #[clap(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
+
+    /// How many cases to run in parallel.
+    #[clap(short, long, default_value_t = 1)]
+    jobs: usize,
+}
+
+#[derive(Args, Debug)]
+struct InspectOpts {
+    #[clap(flatten)]
+    function_opts: FunctionOpts,
+
+    /// Print the module's details as a JSON object
+    #[clap(short, long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct CheckOpts {
+    #[clap(flatten)]
+    function_opts: FunctionOpts,
+
+    /// Print the diagnostics as a JSON array
+    #[clap(short, long)]
+    json: bool,
 }
 
-impl Opts {
+impl RunOpts {
     pub fn profile_opts(&self) -> Option<ProfileOpts> {
         if !self.profile && self.profile_out.is_none() && self.profile_frequency.is_none() {
             return None;
@@ -93,7 +319,8 @@ impl Opts {
         let mut path = PathBuf::new();
 
         path.set_file_name(
-            self.function
+            self.function_opts
+                .function
                 .file_name()
                 .unwrap_or(std::ffi::OsStr::new("function")),
         );
@@ -101,6 +328,109 @@ impl Opts {
 
         path
     }
+}
+
+impl FunctionOpts {
+    /// The config file given with `--config`, otherwise the one in the current directory, if any.
+    pub fn project_config(&self) -> Result<Option<ProjectConfig>> {
+        match &self.config {
+            Some(path) => ProjectConfig::load(path).map(Some),
+            None => ProjectConfig::discover(Path::new(".")),
+        }
+    }
+
+    /// Fill in the options that weren't given on the command line from the project config and the target.
+    pub fn apply_project_config(&mut self, matches: &ArgMatches) -> Result<()> {
+        let Some(config) = self.project_config()? else {
+            if self.target.is_some() {
+                return Err(anyhow!(
+                    "Targets require a {} or {} in the current directory, or --config",
+                    project_config::CONFIG_FILE_NAME,
+                    project_config::SHOPIFY_EXTENSION_FILE_NAME
+                ));
+            }
+            return Ok(());
+        };
+
+        self.apply_target_config(&config, matches)
+    }
+
+    fn apply_target_config(&mut self, config: &ProjectConfig, matches: &ArgMatches) -> Result<()> {
+        let target = config.target(self.target.as_deref())?;
+        let from_command_line =
+            |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let (Some(function), false) = (target.function, from_command_line("function")) {
+            self.function = function;
+        }
+        if let (Some(export), false) = (target.export, from_command_line("export")) {
+            self.export = export;
+        }
+        if let (Some(schema_path), false) = (target.schema_path, from_command_line("schema_path")) {
+            self.schema_path = Some(schema_path);
+        }
+        if let (Some(query_path), false) = (target.query_path, from_command_line("query_path")) {
+            self.query_path = Some(query_path);
+        }
+        if let (Some(codec), false) = (target.codec, from_command_line("codec")) {
+            self.codec = Codec::from_str(&codec, true)
+                .map_err(|e| anyhow!("Invalid codec {:?} in {:?}: {}", codec, config.path, e))?;
+        }
+        if let (Some(output_codec), false) =
+            (target.output_codec, from_command_line("output_codec"))
+        {
+            self.output_codec = Some(OutputCodec::from_str(&output_codec, true).map_err(|e| {
+                anyhow!(
+                    "Invalid output codec {:?} in {:?}: {}",
+                    output_codec,
+                    config.path,
+                    e
+                )
+            })?);
+        }
+        if let (Some(limits), false) = (target.limits, from_command_line("limit_profile")) {
+            self.limit_profile = LimitProfile::load(&limits)?;
+        }
+
+        Ok(())
+    }
+
+    /// The module cache, `None` with `--no-cache`, when the user has no cache directory or when the default one
+    /// can't be used. Only a `--cache-dir` that can't be used fails.
+    pub fn module_cache(&self) -> Result<Option<ModuleCache>> {
+        if self.no_cache {
+            return Ok(None);
+        }
+
+        // the cache is opened for each Function, only warn about it and clear it once
+        let first_open = !MODULE_CACHE_OPENED.swap(true, Ordering::Relaxed);
+        let module_cache = match &self.cache_dir {
+            Some(cache_dir) => ModuleCache::open(cache_dir)?,
+            None => {
+                let Some(cache_dir) = ModuleCache::default_dir() else {
+                    return Ok(None);
+                };
+                match ModuleCache::open(cache_dir) {
+                    Ok(module_cache) => module_cache,
+                    Err(error) => {
+                        if first_open {
+                            eprintln!(
+                                "{}",
+                                format!("warning: running without the module cache: {error}")
+                                    .yellow()
+                            );
+                        }
+                        return Ok(None);
+                    }
+                }
+            }
+        };
+        if self.clear_cache && first_open {
+            module_cache.clear()?;
+        }
+
+        Ok(Some(module_cache))
+    }
 
     pub fn read_schema_to_string(&self) -> Option<Result<String>> {
         self.schema_path.as_ref().map(read_file_to_string)
@@ -109,6 +439,120 @@ impl Opts {
     pub fn read_query_to_string(&self) -> Option<Result<String>> {
         self.query_path.as_ref().map(read_file_to_string)
     }
+
+    pub fn run_options<'a>(
+        &'a self,
+        profile_opts: Option<&'a ProfileOpts>,
+    ) -> Result<RunOptions<'a>> {
+        Ok(RunOptions {
+            profile_opts,
+            scale_factor: self.limit_profile.clamp_scale_factor(DEFAULT_SCALE_FACTOR),
+            enforce_instructions_limit: self.enforce_instructions_limit,
+            timeout: self.timeout.map(Duration::from_millis),
+            memory_limit: Some(self.memory_limit.unwrap_or(self.limit_profile.memory)),
+            limit_profile: Some(&self.limit_profile),
+            input_codec: Some(self.input_codec()?),
+            // the input is encoded by `prepare_input`, which also needs it as JSON
+            encode_input: false,
+            output_codec: Some(self.output_codec()?),
+        })
+    }
+
+    fn auto_codec(&self) -> Result<&AutoCodec> {
+        if let Some(auto_codec) = self.auto_codec.get() {
+            return Ok(auto_codec);
+        }
+
+        let bytes = std::fs::read(&self.function)
+            .map_err(|e| anyhow!("Couldn't load the Function {:?}: {}", self.function, e))?;
+        Ok(self
+            .auto_codec
+            .get_or_init(|| AutoCodec::for_module(&bytes)))
+    }
+
+    fn input_codec(&self) -> Result<&dyn codec::Codec> {
+        Ok(match self.codec {
+            Codec::Json => &JsonCodec,
+            Codec::Raw => &RawCodec,
+            Codec::JsonToMessagepack => &MessagePackCodec,
+            Codec::Auto => self.auto_codec()?,
+        })
+    }
+
+    fn output_codec(&self) -> Result<&dyn codec::Codec> {
+        Ok(
+            match self
+                .output_codec
+                .unwrap_or(self.codec.default_output_codec())
+            {
+                OutputCodec::Json => &JsonCodec,
+                OutputCodec::Messagepack => &MessagePackCodec,
+                OutputCodec::Auto => self.auto_codec()?,
+            },
+        )
+    }
+
+    /// Apply the patches, then the overrides, to the JSON input.
+    fn patch_input(&self, buffer: Vec<u8>) -> Result<Vec<u8>> {
+        if self.patches.is_empty() && self.overrides.is_empty() {
+            return Ok(buffer);
+        }
+
+        let mut input: serde_json::Value = serde_json::from_slice(&buffer).map_err(|e| {
+            anyhow!(
+                "Invalid input JSON, --patch and --set need a JSON input: {}",
+                e
+            )
+        })?;
+        for patch in &self.patches {
+            patch
+                .apply(&mut input)
+                .map_err(|e| anyhow!("Couldn't apply --patch: {}", e))?;
+        }
+        for input_override in &self.overrides {
+            input_override
+                .apply(&mut input)
+                .map_err(|e| anyhow!("Couldn't set {:?}: {}", input_override.pointer, e))?;
+        }
+
+        serde_json::to_vec(&input).map_err(|e| anyhow!("Couldn't serialize JSON: {}", e))
+    }
+
+    /// Encode the input with the codec and compute its scale factor when both a schema and a query are given.
+    pub fn prepare_input(
+        &self,
+        buffer: Vec<u8>,
+        schema_string: Option<&str>,
+        query_string: Option<&str>,
+    ) -> Result<(Vec<u8>, f64)> {
+        let buffer = self.patch_input(buffer)?;
+        let EncodedInput {
+            bytes: buffer,
+            value: json_value,
+        } = engine::encode_input(buffer, Some(self.input_codec()?))?;
+
+        let scale_factor = if let (Some(schema_string), Some(query_string), Some(json_value)) =
+            (schema_string, query_string, json_value)
+        {
+            BluejaySchemaAnalyzer::analyze_schema_definition(
+                schema_string,
+                self.schema_path.as_ref().and_then(|p| p.to_str()),
+                query_string,
+                self.query_path.as_ref().and_then(|p| p.to_str()),
+                &json_value,
+                &self.limit_profile,
+            )?
+        } else {
+            // Use default scale factor when schema or query is missing
+            self.limit_profile.clamp_scale_factor(DEFAULT_SCALE_FACTOR)
+        };
+
+        Ok((buffer, scale_factor))
+    }
+}
+
+fn load_patch(path: &str) -> Result<Patch> {
+    Patch::from_file(Path::new(path))
 }
 
 fn read_file_to_string(file_path: &PathBuf) -> Result<String> {
@@ -122,8 +566,327 @@ fn read_file_to_string(file_path: &PathBuf) -> Result<String> {
     Ok(contents)
 }
 
-fn main() -> Result<()> {
-    let opts: Opts = Opts::parse();
+/// The runner error behind a failed entry, errors preparing its input are reported as invalid input.
+fn run_error(error: anyhow::Error) -> Error {
+    error
+        .downcast()
+        .unwrap_or_else(|error: anyhow::Error| Error::InvalidInput {
+            message: error.to_string(),
+        })
+}
+
+/// Input files, with directories replaced by the `.json` files they contain in alphabetical order.
+fn expand_input_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(path)
+                .map_err(|e| anyhow!("Couldn't read input directory {:?}: {}", path, e))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            entries.retain(|entry| {
+                let is_input = entry.extension().is_some_and(|ext| ext == "json");
+                if !is_input && entry.is_file() {
+                    eprintln!(
+                        "{}",
+                        format!("warning: skipping {entry:?}, only `.json` files in {path:?} are used as inputs")
+                            .yellow()
+                    );
+                }
+                is_input
+            });
+            entries.sort();
+            expanded.extend(entries);
+        } else {
+            expanded.push(path.clone());
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Prepare and run every input, keeping inputs that couldn't be prepared as failed entries in their original position.
+fn run_inputs(
+    function_opts: &FunctionOpts,
+    inputs: Vec<(String, PathBuf)>,
+    jobs: usize,
+) -> Result<Vec<BatchEntry>> {
+    let schema_string = function_opts.read_schema_to_string().transpose()?;
+    let query_string = function_opts.read_query_to_string().transpose()?;
+    let module_cache = function_opts.module_cache()?;
+    let runner = FunctionRunner::from_path(&function_opts.function, module_cache.as_ref())?;
+
+    let mut cases = Vec::new();
+    let mut preparation_errors = Vec::new();
+    for (name, path) in inputs {
+        let prepared = std::fs::read(&path)
+            .map_err(|e| anyhow!("Couldn't load input {:?}: {}", path, e))
+            .and_then(|buffer| {
+                function_opts.prepare_input(
+                    buffer,
+                    schema_string.as_deref(),
+                    query_string.as_deref(),
+                )
+            });
+
+        match prepared {
+            Ok((input, scale_factor)) => cases.push(BatchCase {
+                name,
+                input,
+                scale_factor,
+            }),
+            Err(error) => preparation_errors.push((
+                cases.len(),
+                BatchEntry {
+                    name,
+                    result: Err(run_error(error)),
+                    duration: Duration::ZERO,
+                },
+            )),
+        }
+    }
+
+    let mut entries = run_batch(
+        &runner,
+        &function_opts.export,
+        cases,
+        &function_opts.run_options(None)?,
+        jobs,
+    );
+    for (index, entry) in preparation_errors.into_iter().rev() {
+        entries.insert(index, entry);
+    }
+
+    Ok(entries)
+}
+
+/// Print one JSON object per entry or a summary table, exiting with failure unless every entry passed.
+fn report_entries(entries: &[BatchEntry], ndjson: bool) -> ExitCode {
+    if ndjson {
+        for entry in entries {
+            println!("{}", entry.to_ndjson());
+        }
+    } else {
+        println!("{}", summary_table(entries));
+    }
+
+    if entries.iter().all(BatchEntry::passed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn batch(opts: BatchOpts) -> Result<ExitCode> {
+    let inputs = expand_input_paths(&opts.inputs)?
+        .into_iter()
+        .map(|path| (path.to_string_lossy().into_owned(), path))
+        .collect();
+    let entries = run_inputs(&opts.function_opts, inputs, opts.jobs)?;
+
+    Ok(report_entries(&entries, opts.ndjson))
+}
+
+fn targets(opts: TargetsOpts, matches: &ArgMatches) -> Result<ExitCode> {
+    let config = opts.function_opts.project_config()?.ok_or_else(|| {
+        anyhow!(
+            "Running targets requires a {} or {} in the current directory, or --config",
+            project_config::CONFIG_FILE_NAME,
+            project_config::SHOPIFY_EXTENSION_FILE_NAME
+        )
+    })?;
+    let target_names: Vec<String> = match &opts.function_opts.target {
+        Some(target) => vec![target.clone()],
+        None => config.targets.keys().cloned().collect(),
+    };
+    if target_names.is_empty() {
+        return Err(anyhow!("No targets found in {:?}", config.path));
+    }
+
+    // targets usually share the Function, each module is only compiled once
+    let mut runners: HashMap<PathBuf, FunctionRunner> = HashMap::new();
+    let mut entries = Vec::new();
+    for name in target_names {
+        let start = Instant::now();
+        let mut function_opts = FunctionOpts {
+            target: Some(name.clone()),
+            ..opts.function_opts.clone()
+        };
+        let input_path = opts
+            .inputs
+            .iter()
+            .rev()
+            .find(|(target, _)| *target == name)
+            .map(|(_, path)| path.clone());
+
+        let result = run_target(
+            &mut function_opts,
+            &config,
+            matches,
+            input_path,
+            &mut runners,
+        )
+        .map_err(run_error);
+        entries.push(BatchEntry {
+            name,
+            result,
+            duration: start.elapsed(),
+        });
+    }
+
+    Ok(report_entries(&entries, opts.ndjson))
+}
+
+fn run_target(
+    function_opts: &mut FunctionOpts,
+    config: &ProjectConfig,
+    matches: &ArgMatches,
+    input_path: Option<PathBuf>,
+    runners: &mut HashMap<PathBuf, FunctionRunner>,
+) -> Result<FunctionRunResult> {
+    function_opts.apply_target_config(config, matches)?;
+    let target = function_opts.target.as_deref().unwrap_or_default();
+    let input_path = input_path
+        .or(config.target(Some(target))?.input)
+        .ok_or_else(|| {
+            anyhow!(
+                "No input for target {:?}, set `input` in the config or pass --input {}=PATH",
+                target,
+                target
+            )
+        })?;
+
+    let buffer = std::fs::read(&input_path)
+        .map_err(|e| anyhow!("Couldn't load input {:?}: {}", input_path, e))?;
+    let schema_string = function_opts.read_schema_to_string().transpose()?;
+    let query_string = function_opts.read_query_to_string().transpose()?;
+    let (input, scale_factor) =
+        function_opts.prepare_input(buffer, schema_string.as_deref(), query_string.as_deref())?;
+
+    let runner = match runners.entry(function_opts.function.clone()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert(FunctionRunner::from_path(
+            &function_opts.function,
+            function_opts.module_cache()?.as_ref(),
+        )?),
+    };
+
+    let result = runner.run_with_options(
+        input,
+        &function_opts.export,
+        &RunOptions {
+            scale_factor,
+            ..function_opts.run_options(None)?
+        },
+    )?;
+
+    Ok(result)
+}
+
+fn test(opts: TestOpts) -> Result<ExitCode> {
+    let cases = fixture_tests::discover_cases(&opts.cases_dir)?;
+    if cases.is_empty() {
+        return Err(anyhow!(
+            "No cases found in {:?}, expected sub-directories containing an {}",
+            opts.cases_dir,
+            fixture_tests::INPUT_FILE_NAME
+        ));
+    }
+
+    let inputs = cases
+        .iter()
+        .map(|case| (case.name.clone(), case.input_path()))
+        .collect();
+    let entries = run_inputs(&opts.function_opts, inputs, opts.jobs)?;
+    let results: Vec<FixtureResult> = cases
+        .iter()
+        .zip(&entries)
+        .map(|(case, entry)| fixture_tests::check_case(case, entry, opts.update))
+        .collect();
+
+    println!("{}", fixture_tests::summary(&results));
+
+    if let Some(junit) = &opts.junit {
+        let suite_name = opts.function_opts.function.to_string_lossy();
+        std::fs::write(junit, fixture_tests::junit_report(&suite_name, &results))
+            .map_err(|e| anyhow!("Couldn't write JUnit report {:?}: {}", junit, e))?;
+    }
+
+    Ok(if results.iter().all(FixtureResult::is_success) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn inspect(opts: InspectOpts) -> Result<ExitCode> {
+    let info = ModuleInfo::from_file(&opts.function_opts.function)?;
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!("{info}");
+
+    let export = &opts.function_opts.export;
+    let warning = match info
+        .export(export)
+        .and_then(|export| export.signature.as_ref())
+    {
+        None => Some(format!(
+            "{export:?} isn't an exported function, running with --export {export} will fail"
+        )),
+        Some(signature) if signature != "() -> ()" => Some(format!(
+            "{export:?} has the signature {signature}, exports are called with () -> ()"
+        )),
+        Some(_) => None,
+    };
+    if let Some(warning) = warning {
+        println!("{}", warning.red());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check(opts: CheckOpts) -> Result<ExitCode> {
+    let function_opts = &opts.function_opts;
+    let bytes = std::fs::read(&function_opts.function).map_err(|e| {
+        anyhow!(
+            "Couldn't load the Function {:?}: {}",
+            function_opts.function,
+            e
+        )
+    })?;
+    let diagnostics =
+        module_check::check_module(&bytes, &function_opts.export, &function_opts.limit_profile)
+            .map_err(|e| {
+                anyhow!(
+                    "Couldn't check the Function {:?}: {}",
+                    function_opts.function,
+                    e
+                )
+            })?;
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        println!("{}", module_check::summary(&diagnostics));
+    }
+
+    Ok(if module_check::has_errors(&diagnostics) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn run(opts: RunOpts) -> Result<ExitCode> {
+    if opts.profile_opts().is_some() && opts.function_opts.timeout.is_some() {
+        return Err(anyhow!(
+            "--timeout can't be used with profiling, profiled Functions are never interrupted"
+        ));
+    }
 
     let mut input: Box<dyn Read + Sync + Send + 'static> = if let Some(ref input) = opts.input {
         Box::new(BufReader::new(File::open(input).map_err(|e| {
@@ -140,51 +903,27 @@ fn main() -> Result<()> {
     let mut buffer = Vec::new();
     input.read_to_end(&mut buffer)?;
 
-    let schema_string = opts.read_schema_to_string().transpose()?;
+    let function_opts = &opts.function_opts;
+    let schema_string = function_opts.read_schema_to_string().transpose()?;
 
-    let query_string = opts.read_query_to_string().transpose()?;
+    let query_string = function_opts.read_query_to_string().transpose()?;
 
-    let (json_value, buffer) = match opts.codec {
-        Codec::Json => {
-            let json = serde_json::from_slice::<serde_json::Value>(&buffer)
-                .map_err(|e| anyhow!("Invalid input JSON: {}", e))?;
-            let minified_buffer =
-                serde_json::to_vec(&json).map_err(|e| anyhow!("Couldn't serialize JSON: {}", e))?;
-            (Some(json), minified_buffer)
-        }
-        Codec::Raw => (None, buffer),
-        Codec::JsonToMessagepack => {
-            let json: serde_json::Value = serde_json::from_slice(&buffer)
-                .map_err(|e| anyhow!("Invalid input JSON: {}", e))?;
-            let bytes = rmp_serde::to_vec(&json)
-                .map_err(|e| anyhow!("Couldn't convert JSON to MessagePack: {}", e))?;
-            (Some(json), bytes)
-        }
-    };
-
-    let scale_factor = if let (Some(schema_string), Some(query_string), Some(json_value)) =
-        (schema_string, query_string, json_value)
-    {
-        BluejaySchemaAnalyzer::analyze_schema_definition(
-            &schema_string,
-            opts.schema_path.as_ref().and_then(|p| p.to_str()),
-            &query_string,
-            opts.query_path.as_ref().and_then(|p| p.to_str()),
-            &json_value,
-        )?
-    } else {
-        DEFAULT_SCALE_FACTOR // Use default scale factor when schema or query is missing
-    };
+    let (buffer, scale_factor) =
+        function_opts.prepare_input(buffer, schema_string.as_deref(), query_string.as_deref())?;
 
     let profile_opts = opts.profile_opts();
-
-    let function_run_result = run(FunctionRunParams {
-        function_path: opts.function,
-        input: buffer,
-        export: opts.export.as_ref(),
-        profile_opts: profile_opts.as_ref(),
-        scale_factor,
-    })?;
+    let module_cache = function_opts.module_cache()?;
+
+    let function_run_result =
+        FunctionRunner::from_path(&function_opts.function, module_cache.as_ref())?
+            .run_with_options(
+                buffer,
+                &function_opts.export,
+                &RunOptions {
+                    scale_factor,
+                    ..function_opts.run_options(profile_opts.as_ref())?
+                },
+            )?;
 
     if opts.json {
         println!("{}", function_run_result.to_json());
@@ -196,5 +935,76 @@ fn main() -> Result<()> {
         std::fs::write(profile_opts.unwrap().out, profile)?;
     }
 
-    Ok(())
+    if opts.enforce_limits
+        && !(function_run_result.violations.is_empty()
+            && function_run_result.disallowed_imports.is_empty())
+    {
+        for violation in &function_run_result.violations {
+            eprintln!("Limit exceeded: {violation}");
+        }
+        for disallowed_import in &function_run_result.disallowed_imports {
+            eprintln!("Import not allowed: {disallowed_import}");
+        }
+        return Ok(ExitCode::from(LIMITS_EXCEEDED_EXIT_CODE));
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> Result<ExitCode> {
+    run_command().map_err(|error| match error.downcast_ref() {
+        Some(Error::Export { .. }) => {
+            anyhow!("{error}, use `function-runner inspect` to list the exports")
+        }
+        _ => error,
+    })
+}
+
+fn run_command() -> Result<ExitCode> {
+    let matches = Opts::command().get_matches();
+    let opts = Opts::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    let command_matches = matches
+        .subcommand()
+        .map_or(&matches, |(_, matches)| matches);
+
+    match opts.command {
+        Some(Command::Run(mut run_opts)) => {
+            run_opts
+                .function_opts
+                .apply_project_config(command_matches)?;
+            run(run_opts)
+        }
+        Some(Command::Batch(mut batch_opts)) => {
+            batch_opts
+                .function_opts
+                .apply_project_config(command_matches)?;
+            batch(batch_opts)
+        }
+        Some(Command::Targets(targets_opts)) => targets(targets_opts, command_matches),
+        Some(Command::Test(mut test_opts)) => {
+            test_opts
+                .function_opts
+                .apply_project_config(command_matches)?;
+            test(test_opts)
+        }
+        Some(Command::Inspect(mut inspect_opts)) => {
+            inspect_opts
+                .function_opts
+                .apply_project_config(command_matches)?;
+            inspect(inspect_opts)
+        }
+        Some(Command::Check(mut check_opts)) => {
+            check_opts
+                .function_opts
+                .apply_project_config(command_matches)?;
+            check(check_opts)
+        }
+        None => {
+            let mut run_opts = opts.run_opts;
+            run_opts
+                .function_opts
+                .apply_project_config(command_matches)?;
+            run(run_opts)
+        }
+    }
 }
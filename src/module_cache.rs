@@ -0,0 +1,299 @@
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Write,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use wasmtime::{Engine, Module};
+
+const CHECKSUM_LENGTH: usize = 32;
+
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// On-disk cache of compiled modules.
+///
+/// Entries are keyed by the hash of the wasm bytes and of the engine's compatibility fingerprint, so a
+/// different wasmtime version or `Config` never loads an incompatible artifact. Each entry is prefixed with
+/// a checksum of the serialized module, entries that fail verification or deserialization are recompiled
+/// and overwritten.
+///
+/// Cached modules are native code loaded without validation, so the directory must only be writable by the
+/// current user: it is created with mode 0700, and directories owned by someone else or writable by others are
+/// refused.
+#[derive(Clone, Debug)]
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    /// Use `dir` as the cache, creating it when it doesn't exist.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let cache = Self { dir: dir.into() };
+        create_private_dir(&cache.dir)
+            .map_err(|e| anyhow!("Couldn't create the module cache {:?}: {}", cache.dir, e))?;
+        cache.check_dir()?;
+
+        Ok(cache)
+    }
+
+    /// `function-runner` in the current user's cache directory: `$XDG_CACHE_HOME`, `~/.cache` or, on Windows,
+    /// `%LOCALAPPDATA%`. `None` when none of them is set.
+    pub fn default_dir() -> Option<PathBuf> {
+        let non_empty = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+        let cache_dir = non_empty("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| Path::new(&home).join(".cache")))
+            .or_else(|| non_empty("LOCALAPPDATA").map(PathBuf::from))?;
+
+        Some(cache_dir.join("function-runner"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Remove every compiled module from the cache, returning how many were removed. Entries aren't evicted
+    /// otherwise, the cache grows with each version of a module compiled.
+    pub fn clear(&self) -> Result<usize> {
+        let mut removed = 0;
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| anyhow!("Couldn't read the module cache {:?}: {}", self.dir, e))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "cwasm" || extension == "tmp")
+            {
+                fs::remove_file(&path).map_err(|e| {
+                    anyhow!("Couldn't remove {:?} from the module cache: {}", path, e)
+                })?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Load the compiled module for `bytes` from the cache, compiling and storing it on a miss.
+    ///
+    /// Failing to read or write the cache never fails compilation, the module is compiled instead.
+    pub fn load_or_compile(&self, engine: &Engine, bytes: &[u8]) -> Result<Module> {
+        // the directory may have changed since it was opened, never load from or write to one that isn't private
+        if self.check_dir().is_err() {
+            return Module::new(engine, bytes);
+        }
+        let path = self.entry_path(engine, bytes);
+
+        if let Some(module) = Self::load(engine, &path) {
+            return Ok(module);
+        }
+
+        let module = Module::new(engine, bytes)?;
+        let _ = self.store(&path, &module);
+
+        Ok(module)
+    }
+
+    fn entry_path(&self, engine: &Engine, bytes: &[u8]) -> PathBuf {
+        let mut hasher = Sha256Hasher::default();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        hasher.0.update(bytes);
+
+        self.dir
+            .join(format!("{}.cwasm", to_hex(&hasher.0.finalize())))
+    }
+
+    fn load(engine: &Engine, path: &Path) -> Option<Module> {
+        let contents = fs::read(path).ok()?;
+        if contents.len() < CHECKSUM_LENGTH {
+            return None;
+        }
+
+        let (checksum, serialized) = contents.split_at(CHECKSUM_LENGTH);
+        if Sha256::digest(serialized).as_slice() != checksum {
+            return None;
+        }
+
+        // SAFETY: only the current user can write to the cache directory, see `check_dir`, so the entry was
+        // written by `store` from `Module::serialize` for an engine with the same compatibility hash. The
+        // checksum, which anyone able to write the entry could recompute, only catches accidental corruption such
+        // as a truncated write, and wasmtime additionally validates the artifact's header.
+        unsafe { Module::deserialize(engine, serialized) }.ok()
+    }
+
+    fn store(&self, path: &Path, module: &Module) -> Result<()> {
+        let serialized = module.serialize()?;
+        let mut contents = Vec::with_capacity(CHECKSUM_LENGTH + serialized.len());
+        contents.extend_from_slice(&Sha256::digest(&serialized));
+        contents.extend_from_slice(&serialized);
+
+        // write to a temporary file first so concurrent runs never observe a partially written entry
+        let temp_path = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, path).inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })?;
+
+        Ok(())
+    }
+}
+
+impl ModuleCache {
+    /// Fail unless the cache directory is owned by the current user and nobody else can write to it.
+    #[cfg(unix)]
+    fn check_dir(&self) -> Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = fs::metadata(&self.dir)
+            .map_err(|e| anyhow!("Couldn't read the module cache {:?}: {}", self.dir, e))?;
+        // SAFETY: `geteuid` has no preconditions and can't fail.
+        let user = unsafe { libc::geteuid() };
+        if metadata.uid() != user {
+            return Err(anyhow!(
+                "The module cache {:?} is owned by another user",
+                self.dir
+            ));
+        }
+        if metadata.mode() & 0o022 != 0 {
+            return Err(anyhow!(
+                "The module cache {:?} is writable by other users, restrict it with `chmod 700` or use --no-cache",
+                self.dir
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Per-user directories such as `%LOCALAPPDATA%` are private on Windows.
+    #[cfg(not(unix))]
+    fn check_dir(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+/// Feeds `Hash` implementations into a SHA-256 digest.
+#[derive(Default)]
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap_or_default())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::new_engine;
+
+    fn cache_entries(cache: &ModuleCache) -> Vec<PathBuf> {
+        fs::read_dir(cache.dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect()
+    }
+
+    #[test]
+    fn test_caches_compiled_module() -> Result<()> {
+        let temp = assert_fs::TempDir::new()?;
+        let cache = ModuleCache::open(temp.path())?;
+        let engine = new_engine()?;
+        let bytes = fs::read("tests/fixtures/build/exit_code.wasm")?;
+
+        cache.load_or_compile(&engine, &bytes)?;
+        let entries = cache_entries(&cache);
+        assert_eq!(entries.len(), 1);
+
+        let cached = ModuleCache::load(&engine, &entries[0]);
+        assert!(cached.is_some(), "Expected the cached module to load");
+
+        cache.load_or_compile(&engine, &bytes)?;
+        assert_eq!(cache_entries(&cache), entries);
+
+        assert_eq!(cache.clear()?, 1);
+        assert!(cache_entries(&cache).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recompiles_corrupted_entry() -> Result<()> {
+        let temp = assert_fs::TempDir::new()?;
+        let cache = ModuleCache::open(temp.path())?;
+        let engine = new_engine()?;
+        let bytes = fs::read("tests/fixtures/build/exit_code.wasm")?;
+
+        cache.load_or_compile(&engine, &bytes)?;
+        let entry = cache_entries(&cache).remove(0);
+        let mut contents = fs::read(&entry)?;
+        let last = contents.len() - 1;
+        contents[last] ^= 0xff;
+        fs::write(&entry, &contents)?;
+        assert!(ModuleCache::load(&engine, &entry).is_none());
+
+        cache.load_or_compile(&engine, &bytes)?;
+        assert!(ModuleCache::load(&engine, &entry).is_some());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_refuses_shared_directory() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = assert_fs::TempDir::new()?;
+        let dir = temp.path().join("cache");
+        let cache = ModuleCache::open(&dir)?;
+        assert_eq!(fs::metadata(&dir)?.permissions().mode() & 0o777, 0o700);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777))?;
+        let error = ModuleCache::open(&dir).unwrap_err();
+        assert!(
+            error.to_string().contains("is writable by other users"),
+            "{error}"
+        );
+
+        // a directory that turns writable by others after opening it isn't used either
+        let engine = new_engine()?;
+        let bytes = fs::read("tests/fixtures/build/exit_code.wasm")?;
+        cache.load_or_compile(&engine, &bytes)?;
+        assert!(cache_entries(&cache).is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,386 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+use wasmparser::{
+    ExternalKind, KnownCustom, Name, Operator, Parser, Payload, TypeRef, Validator, WasmFeatures,
+};
+
+use crate::{
+    engine::{is_standard_provider, standard_provider_names},
+    limit_profile::{LimitProfile, WASI_MODULE_NAME},
+    module_info::{ModuleInfo, WASM_PAGE_SIZE},
+};
+
+/// Signature every exported entrypoint must have.
+pub const ENTRYPOINT_SIGNATURE: &str = "() -> ()";
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The module would be rejected, or couldn't run, in production.
+    Error,
+    /// The module may behave differently in production.
+    Warning,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(formatter, "{}: {}", "error".red(), self.message),
+            Severity::Warning => write!(formatter, "{}: {}", "warning".yellow(), self.message),
+        }
+    }
+}
+
+/// An import the limit profile doesn't allow.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DisallowedImport {
+    pub module: String,
+    pub name: String,
+    /// Position of the import in the module's import section.
+    pub index: u32,
+    /// Functions calling the import, named after the name section or their export when possible.
+    pub callers: Vec<String>,
+}
+
+impl DisallowedImport {
+    /// Whether the runner can still link the import, as it comes from WASI or a provider.
+    pub fn is_linkable(&self) -> bool {
+        self.module == WASI_MODULE_NAME || is_standard_provider(&self.module)
+    }
+}
+
+impl fmt::Display for DisallowedImport {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}::{} (import #{}) isn't allowed by the limit profile",
+            self.module, self.name, self.index
+        )?;
+        if !self.is_linkable() {
+            write!(
+                formatter,
+                ", {:?} is neither {WASI_MODULE_NAME} nor one of the providers: {}",
+                self.module,
+                standard_provider_names().join(", ")
+            )?;
+        }
+        match self.callers.as_slice() {
+            [] => Ok(()),
+            callers => write!(formatter, ", called from {}", callers.join(", ")),
+        }
+    }
+}
+
+/// The imports of a binary module that `profile` doesn't allow, along with the functions calling them.
+pub fn disallowed_imports(bytes: &[u8], profile: &LimitProfile) -> Result<Vec<DisallowedImport>> {
+    let mut disallowed = vec![];
+    // Function index of each disallowed function import, and the functions calling it.
+    let mut disallowed_functions: BTreeMap<u32, usize> = BTreeMap::new();
+    let mut callers: BTreeMap<usize, BTreeSet<u32>> = BTreeMap::new();
+    let mut function_names: BTreeMap<u32, String> = BTreeMap::new();
+    let mut imported_functions = 0;
+    let mut defined_functions = 0;
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload.map_err(|e| anyhow!("Invalid module: {}", e))? {
+            Payload::ImportSection(reader) => {
+                for (index, import) in reader.into_iter().enumerate() {
+                    let import = import.map_err(|e| anyhow!("Invalid module: {}", e))?;
+                    if !profile.allows_import(import.module, import.name) {
+                        if let TypeRef::Func(_) = import.ty {
+                            disallowed_functions.insert(imported_functions, disallowed.len());
+                        }
+                        disallowed.push(DisallowedImport {
+                            module: import.module.to_string(),
+                            name: import.name.to_string(),
+                            index: index as u32,
+                            callers: vec![],
+                        });
+                    }
+                    if let TypeRef::Func(_) = import.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) if !disallowed_functions.is_empty() => {
+                let caller = imported_functions + defined_functions;
+                defined_functions += 1;
+                let operators = body
+                    .get_operators_reader()
+                    .map_err(|e| anyhow!("Invalid module: {}", e))?;
+                for operator in operators {
+                    match operator.map_err(|e| anyhow!("Invalid module: {}", e))? {
+                        Operator::Call { function_index }
+                        | Operator::ReturnCall { function_index } => {
+                            if let Some(import) = disallowed_functions.get(&function_index) {
+                                callers.entry(*import).or_default().insert(caller);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export.map_err(|e| anyhow!("Invalid module: {}", e))?;
+                    if export.kind == ExternalKind::Func {
+                        function_names
+                            .entry(export.index)
+                            .or_insert_with(|| export.name.to_string());
+                    }
+                }
+            }
+            Payload::CustomSection(reader) if !disallowed_functions.is_empty() => {
+                if let KnownCustom::Name(names) = reader.as_known() {
+                    for name in names.into_iter().flatten() {
+                        if let Name::Function(map) = name {
+                            for naming in map.into_iter().flatten() {
+                                function_names.insert(naming.index, naming.name.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (import, caller_indexes) in callers {
+        disallowed[import].callers = caller_indexes
+            .into_iter()
+            .map(|index| {
+                function_names
+                    .get(&index)
+                    .cloned()
+                    .unwrap_or_else(|| format!("func[{index}]"))
+            })
+            .collect();
+    }
+
+    Ok(disallowed)
+}
+
+/// The proposals enabled by [`crate::engine::new_engine`], modules using any other one are rejected.
+fn production_features() -> WasmFeatures {
+    let mut features = WasmFeatures::default();
+    features.remove(
+        WasmFeatures::THREADS | WasmFeatures::REFERENCE_TYPES | WasmFeatures::COMPONENT_MODEL,
+    );
+    features.insert(WasmFeatures::MULTI_MEMORY);
+    features
+}
+
+/// Statically check a binary or text module against the production constraints, without running it.
+pub fn check_module(bytes: &[u8], export: &str, profile: &LimitProfile) -> Result<Vec<Diagnostic>> {
+    let bytes = wat::parse_bytes(bytes).map_err(|e| anyhow!("Invalid module: {}", e))?;
+    let mut diagnostics = vec![];
+
+    if let Err(e) = Validator::new_with_features(production_features()).validate_all(&bytes) {
+        diagnostics.push(Diagnostic::error(format!(
+            "the module is invalid or uses a disallowed proposal: {} (at offset {:#x})",
+            e.message(),
+            e.offset()
+        )));
+    }
+
+    let info = match ModuleInfo::parse(&bytes) {
+        Ok(info) => info,
+        Err(_) if !diagnostics.is_empty() => return Ok(diagnostics),
+        Err(e) => return Err(e),
+    };
+
+    if info.size as u64 > profile.module_size {
+        diagnostics.push(Diagnostic::error(format!(
+            "the module is {} bytes, above the {} bytes upload limit",
+            info.size, profile.module_size
+        )));
+    }
+
+    // imports the runner can link only fail when the profile enforces its allowed imports
+    diagnostics.extend(disallowed_imports(&bytes, profile)?.iter().map(|import| {
+        if profile.enforce_imports || !import.is_linkable() {
+            Diagnostic::error(import.to_string())
+        } else {
+            Diagnostic::warning(import.to_string())
+        }
+    }));
+
+    match info
+        .export(export)
+        .and_then(|export| export.signature.as_ref())
+    {
+        None => diagnostics.push(Diagnostic::error(format!(
+            "no exported function named {export:?}"
+        ))),
+        Some(signature) if signature != ENTRYPOINT_SIGNATURE => {
+            diagnostics.push(Diagnostic::error(format!(
+                "export {export:?} has the signature {signature}, expected {ENTRYPOINT_SIGNATURE}"
+            )))
+        }
+        Some(_) => {}
+    }
+
+    for memory in &info.memories {
+        if memory.initial * WASM_PAGE_SIZE > profile.memory {
+            diagnostics.push(Diagnostic::error(format!(
+                "memory {} starts at {} bytes, above the {} bytes linear memory limit",
+                memory.index,
+                memory.initial * WASM_PAGE_SIZE,
+                profile.memory
+            )));
+        } else if let Some(maximum) = memory.maximum {
+            if maximum * WASM_PAGE_SIZE > profile.memory {
+                diagnostics.push(Diagnostic::warning(format!(
+                    "memory {} declares a maximum of {} bytes, growing beyond the {} bytes linear memory limit will fail",
+                    memory.index,
+                    maximum * WASM_PAGE_SIZE,
+                    profile.memory
+                )));
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// The diagnostics followed by a count of the errors and warnings.
+pub fn summary(diagnostics: &[Diagnostic]) -> String {
+    let count = |severity, noun: &str| {
+        let count = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count();
+        match count {
+            1 => format!("1 {noun}"),
+            count => format!("{count} {noun}s"),
+        }
+    };
+
+    diagnostics
+        .iter()
+        .map(ToString::to_string)
+        .chain(std::iter::once(format!(
+            "{}, {}",
+            count(Severity::Error, "error"),
+            count(Severity::Warning, "warning")
+        )))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_check_valid_module() -> Result<()> {
+        let diagnostics = check_module(
+            br#"(module
+                (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "_start"))
+            )"#,
+            "_start",
+            &LimitProfile::default(),
+        )?;
+        assert_eq!(diagnostics, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_reports_every_problem() -> Result<()> {
+        let profile = LimitProfile {
+            memory: 2 * WASM_PAGE_SIZE,
+            ..LimitProfile::default()
+        };
+        let diagnostics = check_module(
+            br#"(module
+                (import "wasi_snapshot_preview1" "path_open" (func (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+                (import "env" "abort" (func $abort))
+                (memory 3)
+                (memory 1 4)
+                (func (export "run") (param i32) (call $abort))
+            )"#,
+            "run",
+            &profile,
+        )?;
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                "wasi_snapshot_preview1::path_open (import #0) isn't allowed by the limit profile",
+                "env::abort (import #1) isn't allowed by the limit profile, \"env\" is neither wasi_snapshot_preview1 nor one of the providers: javy_quickjs_provider_v1, javy_quickjs_provider_v2, javy_quickjs_provider_v3, called from run",
+                "export \"run\" has the signature (i32) -> (), expected () -> ()",
+                "memory 0 starts at 196608 bytes, above the 131072 bytes linear memory limit",
+                "memory 1 declares a maximum of 262144 bytes, growing beyond the 131072 bytes linear memory limit will fail",
+            ]
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[4].severity, Severity::Warning);
+        assert!(has_errors(&diagnostics));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_disallowed_proposals() -> Result<()> {
+        let diagnostics = check_module(
+            br#"(module
+                (memory 1 1 shared)
+                (func (export "_start"))
+            )"#,
+            "_start",
+            &LimitProfile::default(),
+        )?;
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0].message.contains("threads must be enabled"),
+            "{}",
+            diagnostics[0]
+        );
+
+        Ok(())
+    }
+}
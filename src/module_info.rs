@@ -0,0 +1,413 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, path::Path};
+use wasmparser::{CompositeType, ExternalKind, FuncType, MemoryType, Parser, Payload, TypeRef};
+
+use crate::engine::is_standard_provider;
+
+/// Size of a linear memory page in bytes.
+pub const WASM_PAGE_SIZE: u64 = 65536;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExportInfo {
+    pub name: String,
+    pub kind: &'static str,
+    /// Signature of exported functions, e.g. `() -> ()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ImportInfo {
+    pub name: String,
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// The items imported from one module.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportedModule {
+    /// Whether the module is one of the providers embedded in the runner.
+    pub provider: bool,
+    pub imports: Vec<ImportInfo>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryInfo {
+    pub index: u32,
+    /// `module::name` of imported memories.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub import: Option<String>,
+    /// In pages of 64KiB.
+    pub initial: u64,
+    pub maximum: Option<u64>,
+    pub memory64: bool,
+    pub shared: bool,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CustomSection {
+    pub name: String,
+    /// In bytes.
+    pub size: usize,
+}
+
+/// What a module exports, imports and declares, read without compiling it.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// Size of the binary module in bytes.
+    pub size: usize,
+    pub exports: Vec<ExportInfo>,
+    pub imports: BTreeMap<String, ImportedModule>,
+    pub memories: Vec<MemoryInfo>,
+    pub custom_sections: Vec<CustomSection>,
+}
+
+fn external_kind_name(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Func => "func",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+        ExternalKind::Tag => "tag",
+    }
+}
+
+fn type_ref_kind_name(type_ref: &TypeRef) -> &'static str {
+    match type_ref {
+        TypeRef::Func(_) => "func",
+        TypeRef::Table(_) => "table",
+        TypeRef::Memory(_) => "memory",
+        TypeRef::Global(_) => "global",
+        TypeRef::Tag(_) => "tag",
+    }
+}
+
+/// Format a function type as `(i32, i32) -> i32`, `()` stands for no parameters or results.
+pub fn format_signature(func_type: &FuncType) -> String {
+    let list = |types: &[wasmparser::ValType]| match types {
+        [single] => single.to_string(),
+        types => format!(
+            "({})",
+            types
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    format!(
+        "({}) -> {}",
+        func_type
+            .params()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        list(func_type.results())
+    )
+}
+
+fn memory_info(index: u32, import: Option<String>, memory_type: MemoryType) -> MemoryInfo {
+    MemoryInfo {
+        index,
+        import,
+        initial: memory_type.initial,
+        maximum: memory_type.maximum,
+        memory64: memory_type.memory64,
+        shared: memory_type.shared,
+    }
+}
+
+impl ModuleInfo {
+    /// Read the sections of a binary or text module.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let bytes = wat::parse_bytes(bytes).map_err(|e| anyhow!("Invalid module: {}", e))?;
+        let mut info = ModuleInfo {
+            size: bytes.len(),
+            ..ModuleInfo::default()
+        };
+        // Function types by type index, and type indexes of the functions by function index.
+        let mut types: Vec<Option<FuncType>> = vec![];
+        let mut function_types: Vec<u32> = vec![];
+        let signature = |types: &[Option<FuncType>], type_index: u32| {
+            types
+                .get(type_index as usize)
+                .and_then(Option::as_ref)
+                .map(format_signature)
+        };
+
+        for payload in Parser::new(0).parse_all(&bytes) {
+            match payload.map_err(|e| anyhow!("Invalid module: {}", e))? {
+                Payload::TypeSection(reader) => {
+                    for rec_group in reader {
+                        let rec_group = rec_group.map_err(|e| anyhow!("Invalid module: {}", e))?;
+                        types.extend(rec_group.into_types().map(|sub_type| {
+                            match sub_type.composite_type {
+                                CompositeType::Func(func_type) => Some(func_type),
+                                _ => None,
+                            }
+                        }));
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import.map_err(|e| anyhow!("Invalid module: {}", e))?;
+                        let import_signature = match import.ty {
+                            TypeRef::Func(type_index) => {
+                                function_types.push(type_index);
+                                signature(&types, type_index)
+                            }
+                            TypeRef::Memory(memory_type) => {
+                                info.memories.push(memory_info(
+                                    info.memories.len() as u32,
+                                    Some(format!("{}::{}", import.module, import.name)),
+                                    memory_type,
+                                ));
+                                None
+                            }
+                            _ => None,
+                        };
+                        info.imports
+                            .entry(import.module.to_string())
+                            .or_insert_with(|| ImportedModule {
+                                provider: is_standard_provider(import.module),
+                                imports: vec![],
+                            })
+                            .imports
+                            .push(ImportInfo {
+                                name: import.name.to_string(),
+                                kind: type_ref_kind_name(&import.ty),
+                                signature: import_signature,
+                            });
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for type_index in reader {
+                        function_types
+                            .push(type_index.map_err(|e| anyhow!("Invalid module: {}", e))?);
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for memory_type in reader {
+                        let memory_type =
+                            memory_type.map_err(|e| anyhow!("Invalid module: {}", e))?;
+                        info.memories.push(memory_info(
+                            info.memories.len() as u32,
+                            None,
+                            memory_type,
+                        ));
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(|e| anyhow!("Invalid module: {}", e))?;
+                        let export_signature = match export.kind {
+                            ExternalKind::Func => function_types
+                                .get(export.index as usize)
+                                .and_then(|type_index| signature(&types, *type_index)),
+                            _ => None,
+                        };
+                        info.exports.push(ExportInfo {
+                            name: export.name.to_string(),
+                            kind: external_kind_name(export.kind),
+                            signature: export_signature,
+                        });
+                    }
+                }
+                Payload::CustomSection(reader) => info.custom_sections.push(CustomSection {
+                    name: reader.name().to_string(),
+                    size: reader.data().len(),
+                }),
+                _ => {}
+            }
+        }
+
+        Ok(info)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow!("Couldn't load the Function {:?}: {}", path, e))?;
+
+        Self::parse(&bytes).map_err(|e| anyhow!("Couldn't inspect the Function {:?}: {}", path, e))
+    }
+
+    /// The exported function with this name.
+    pub fn export(&self, name: &str) -> Option<&ExportInfo> {
+        self.exports
+            .iter()
+            .find(|export| export.kind == "func" && export.name == name)
+    }
+}
+
+impl fmt::Display for ModuleInfo {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            formatter,
+            "{}\n\n{:.2}KB ({} bytes)\n",
+            "        Module Size        ".black().on_bright_magenta(),
+            self.size as f64 / 1024.0,
+            self.size
+        )?;
+
+        writeln!(
+            formatter,
+            "{}\n",
+            "          Exports          ".black().on_bright_green()
+        )?;
+        for export in &self.exports {
+            match &export.signature {
+                Some(signature) => writeln!(
+                    formatter,
+                    "  {}: {} {}",
+                    export.name, export.kind, signature
+                )?,
+                None => writeln!(formatter, "  {}: {}", export.name, export.kind)?,
+            }
+        }
+
+        writeln!(
+            formatter,
+            "\n{}\n",
+            "          Imports          ".black().on_bright_blue()
+        )?;
+        for (module, imported_module) in &self.imports {
+            if imported_module.provider {
+                writeln!(formatter, "  {module} {}", "(embedded provider)".dimmed())?;
+            } else {
+                writeln!(formatter, "  {module}")?;
+            }
+            for import in &imported_module.imports {
+                match &import.signature {
+                    Some(signature) => writeln!(
+                        formatter,
+                        "    {}: {} {}",
+                        import.name, import.kind, signature
+                    )?,
+                    None => writeln!(formatter, "    {}: {}", import.name, import.kind)?,
+                }
+            }
+        }
+
+        writeln!(
+            formatter,
+            "\n{}\n",
+            "          Memories          ".black().on_bright_yellow()
+        )?;
+        for memory in &self.memories {
+            let maximum = match memory.maximum {
+                Some(maximum) => format!("{maximum} pages maximum"),
+                None => "no maximum".to_string(),
+            };
+            write!(
+                formatter,
+                "  {}: {} pages initial, {}",
+                memory.index, memory.initial, maximum
+            )?;
+            if let Some(import) = &memory.import {
+                write!(formatter, ", imported from {import}")?;
+            }
+            if memory.memory64 {
+                write!(formatter, ", 64-bit")?;
+            }
+            if memory.shared {
+                write!(formatter, ", shared")?;
+            }
+            writeln!(formatter)?;
+        }
+
+        writeln!(
+            formatter,
+            "\n{}\n",
+            "      Custom Sections      ".black().on_bright_cyan()
+        )?;
+        for section in &self.custom_sections {
+            writeln!(formatter, "  {}: {} bytes", section.name, section.size)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_module() -> Result<()> {
+        let info = ModuleInfo::parse(
+            br#"(module
+                (import "javy_quickjs_provider_v1" "memory" (memory 1))
+                (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+                (memory 2 16)
+                (func (export "run"))
+                (func (export "add") (param i32 i64) (result i64) unreachable)
+                (export "data" (memory 1))
+                (@custom "producers" "abc")
+            )"#,
+        )?;
+
+        assert_eq!(
+            info.exports,
+            vec![
+                ExportInfo {
+                    name: "run".to_string(),
+                    kind: "func",
+                    signature: Some("() -> ()".to_string()),
+                },
+                ExportInfo {
+                    name: "add".to_string(),
+                    kind: "func",
+                    signature: Some("(i32, i64) -> i64".to_string()),
+                },
+                ExportInfo {
+                    name: "data".to_string(),
+                    kind: "memory",
+                    signature: None,
+                },
+            ]
+        );
+        assert!(info.imports["javy_quickjs_provider_v1"].provider);
+        assert!(!info.imports["wasi_snapshot_preview1"].provider);
+        assert_eq!(
+            info.imports["wasi_snapshot_preview1"].imports[0].signature,
+            Some("(i32, i32, i32, i32) -> i32".to_string())
+        );
+        assert_eq!(
+            info.memories,
+            vec![
+                MemoryInfo {
+                    index: 0,
+                    import: Some("javy_quickjs_provider_v1::memory".to_string()),
+                    initial: 1,
+                    maximum: None,
+                    memory64: false,
+                    shared: false,
+                },
+                MemoryInfo {
+                    index: 1,
+                    import: None,
+                    initial: 2,
+                    maximum: Some(16),
+                    memory64: false,
+                    shared: false,
+                },
+            ]
+        );
+        assert_eq!(
+            info.custom_sections,
+            vec![CustomSection {
+                name: "producers".to_string(),
+                size: 3,
+            }]
+        );
+        assert!(info.export("add").is_some());
+        assert!(info.export("data").is_none());
+
+        Ok(())
+    }
+}
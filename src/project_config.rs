@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::limit_profile::LimitProfile;
+
+pub const CONFIG_FILE_NAME: &str = "function-runner.toml";
+pub const SHOPIFY_EXTENSION_FILE_NAME: &str = "shopify.extension.toml";
+const SHOPIFY_SCHEMA_FILE_NAME: &str = "schema.graphql";
+
+/// How to run a Function, or one of its targets. Paths are relative to the config file.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TargetConfig {
+    pub function: Option<PathBuf>,
+    pub export: Option<String>,
+    pub schema_path: Option<PathBuf>,
+    pub query_path: Option<PathBuf>,
+    pub codec: Option<String>,
+    pub output_codec: Option<String>,
+    /// Name of a built-in limit profile or path to a limit profile file.
+    pub limits: Option<String>,
+    /// Input used when running every target at once.
+    pub input: Option<PathBuf>,
+}
+
+impl TargetConfig {
+    /// Values from `self`, falling back to `defaults` for the missing ones.
+    fn or(self, defaults: &TargetConfig) -> TargetConfig {
+        TargetConfig {
+            function: self.function.or_else(|| defaults.function.clone()),
+            export: self.export.or_else(|| defaults.export.clone()),
+            schema_path: self.schema_path.or_else(|| defaults.schema_path.clone()),
+            query_path: self.query_path.or_else(|| defaults.query_path.clone()),
+            codec: self.codec.or_else(|| defaults.codec.clone()),
+            output_codec: self.output_codec.or_else(|| defaults.output_codec.clone()),
+            limits: self.limits.or_else(|| defaults.limits.clone()),
+            input: self.input.or_else(|| defaults.input.clone()),
+        }
+    }
+
+    fn relative_to(self, dir: &Path) -> TargetConfig {
+        TargetConfig {
+            function: self.function.map(|path| dir.join(path)),
+            schema_path: self.schema_path.map(|path| dir.join(path)),
+            query_path: self.query_path.map(|path| dir.join(path)),
+            input: self.input.map(|path| dir.join(path)),
+            limits: self
+                .limits
+                .map(|limits| match LimitProfile::preset(&limits) {
+                    Some(_) => limits,
+                    None => dir.join(limits).to_string_lossy().into_owned(),
+                }),
+            ..self
+        }
+    }
+}
+
+/// `function-runner.toml`: settings shared by every target, and per-target overrides.
+///
+/// ```toml
+/// function = "target/wasm32-wasip1/release/discount.wasm"
+/// schema_path = "schema.graphql"
+///
+/// [targets."purchase.product-discount.run"]
+/// export = "run"
+/// query_path = "src/run.graphql"
+/// input = "inputs/run.json"
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    function: Option<PathBuf>,
+    export: Option<String>,
+    schema_path: Option<PathBuf>,
+    query_path: Option<PathBuf>,
+    codec: Option<String>,
+    output_codec: Option<String>,
+    limits: Option<String>,
+    input: Option<PathBuf>,
+    targets: BTreeMap<String, TargetConfig>,
+}
+
+impl ConfigFile {
+    fn into_targets(self) -> (TargetConfig, BTreeMap<String, TargetConfig>) {
+        let defaults = TargetConfig {
+            function: self.function,
+            export: self.export,
+            schema_path: self.schema_path,
+            query_path: self.query_path,
+            codec: self.codec,
+            output_codec: self.output_codec,
+            limits: self.limits,
+            input: self.input,
+        };
+
+        (defaults, self.targets)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ShopifyExtensionFile {
+    extensions: Vec<ShopifyExtension>,
+    #[serde(flatten)]
+    extension: ShopifyExtension,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ShopifyExtension {
+    targeting: Vec<ShopifyTargeting>,
+    build: ShopifyBuild,
+}
+
+#[derive(Deserialize)]
+struct ShopifyTargeting {
+    target: String,
+    input_query: Option<PathBuf>,
+    export: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ShopifyBuild {
+    path: Option<PathBuf>,
+}
+
+/// Settings read from a `function-runner.toml` or from the `shopify.extension.toml` of a Function extension.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    pub defaults: TargetConfig,
+    pub targets: BTreeMap<String, TargetConfig>,
+}
+
+impl ProjectConfig {
+    /// Read a config file, files named `shopify.extension.toml` are read as Function extensions.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Couldn't load config {:?}: {}", path, e))?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let (defaults, targets) = if path
+            .file_name()
+            .is_some_and(|name| name == SHOPIFY_EXTENSION_FILE_NAME)
+        {
+            Self::parse_shopify_extension(&contents, dir)
+        } else {
+            toml::from_str::<ConfigFile>(&contents).map(ConfigFile::into_targets)
+        }
+        .map_err(|e| anyhow!("Invalid config {:?}: {}", path, e))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            defaults: defaults.relative_to(dir),
+            targets: targets
+                .into_iter()
+                .map(|(name, target)| (name, target.relative_to(dir)))
+                .collect(),
+        })
+    }
+
+    /// The `function-runner.toml` in `dir`, otherwise its `shopify.extension.toml`.
+    pub fn discover(dir: &Path) -> Result<Option<Self>> {
+        [CONFIG_FILE_NAME, SHOPIFY_EXTENSION_FILE_NAME]
+            .iter()
+            .map(|file_name| dir.join(file_name))
+            .find(|path| path.is_file())
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+
+    fn parse_shopify_extension(
+        contents: &str,
+        dir: &Path,
+    ) -> Result<(TargetConfig, BTreeMap<String, TargetConfig>), toml::de::Error> {
+        let file: ShopifyExtensionFile = toml::from_str(contents)?;
+        let extensions: Vec<ShopifyExtension> = std::iter::once(file.extension)
+            .chain(file.extensions)
+            .collect();
+
+        let defaults = TargetConfig {
+            function: extensions
+                .iter()
+                .find_map(|extension| extension.build.path.clone()),
+            schema_path: dir
+                .join(SHOPIFY_SCHEMA_FILE_NAME)
+                .is_file()
+                .then(|| PathBuf::from(SHOPIFY_SCHEMA_FILE_NAME)),
+            ..TargetConfig::default()
+        };
+        let targets = extensions
+            .into_iter()
+            .flat_map(|extension| extension.targeting)
+            .map(|targeting| {
+                (
+                    targeting.target,
+                    TargetConfig {
+                        export: targeting.export,
+                        query_path: targeting.input_query,
+                        ..TargetConfig::default()
+                    },
+                )
+            })
+            .collect();
+
+        Ok((defaults, targets))
+    }
+
+    /// The settings for `target` merged with the shared settings. Without a target, the only target is used
+    /// when there is exactly one.
+    pub fn target(&self, target: Option<&str>) -> Result<TargetConfig> {
+        let target_config = match target {
+            Some(name) => self.targets.get(name).ok_or_else(|| {
+                anyhow!(
+                    "Unknown target {:?} in {:?}, expected one of: {}",
+                    name,
+                    self.path,
+                    self.targets
+                        .keys()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?,
+            None if self.targets.len() == 1 => self.targets.values().next().unwrap(),
+            None => return Ok(self.defaults.clone()),
+        };
+
+        Ok(target_config.clone().or(&self.defaults))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config_file() -> Result<()> {
+        let temp = assert_fs::TempDir::new()?;
+        let path = temp.path().join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &path,
+            r#"
+            function = "build/discount.wasm"
+            schema_path = "schema.graphql"
+            limits = "unscaled"
+
+            [targets."purchase.product-discount.run"]
+            export = "run"
+            query_path = "src/run.graphql"
+
+            [targets."purchase.shipping-discount.run"]
+            export = "run_shipping"
+            limits = "limits/shipping.toml"
+            "#,
+        )?;
+
+        let config = ProjectConfig::load(&path)?;
+        assert_eq!(
+            config.target(Some("purchase.product-discount.run"))?,
+            TargetConfig {
+                function: Some(temp.path().join("build/discount.wasm")),
+                export: Some("run".to_string()),
+                schema_path: Some(temp.path().join("schema.graphql")),
+                query_path: Some(temp.path().join("src/run.graphql")),
+                codec: None,
+                output_codec: None,
+                limits: Some("unscaled".to_string()),
+                input: None,
+            }
+        );
+        assert_eq!(
+            config
+                .target(Some("purchase.shipping-discount.run"))?
+                .limits,
+            Some(
+                temp.path()
+                    .join("limits/shipping.toml")
+                    .to_string_lossy()
+                    .into_owned()
+            )
+        );
+        assert_eq!(config.target(None)?, config.defaults);
+
+        let error = config.target(Some("cart.transform.run")).unwrap_err();
+        assert!(error.to_string().contains(
+            "expected one of: purchase.product-discount.run, purchase.shipping-discount.run"
+        ));
+
+        std::fs::write(&path, "fuction = \"typo.wasm\"")?;
+        assert!(ProjectConfig::load(&path).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_shopify_extension() -> Result<()> {
+        let temp = assert_fs::TempDir::new()?;
+        let path = temp.path().join(SHOPIFY_EXTENSION_FILE_NAME);
+        std::fs::write(temp.path().join("schema.graphql"), "")?;
+        std::fs::write(
+            &path,
+            r#"
+            api_version = "2024-07"
+
+            [[extensions]]
+            name = "Product discount"
+            handle = "product-discount"
+            type = "function"
+
+              [[extensions.targeting]]
+              target = "purchase.product-discount.run"
+              input_query = "src/run.graphql"
+              export = "run"
+
+              [extensions.build]
+              command = "cargo build --target=wasm32-wasip1 --release"
+              path = "target/wasm32-wasip1/release/product-discount.wasm"
+            "#,
+        )?;
+
+        let config = ProjectConfig::discover(temp.path())?.expect("Expected a config");
+        assert_eq!(
+            config.target(None)?,
+            TargetConfig {
+                function: Some(
+                    temp.path()
+                        .join("target/wasm32-wasip1/release/product-discount.wasm")
+                ),
+                export: Some("run".to_string()),
+                schema_path: Some(temp.path().join("schema.graphql")),
+                query_path: Some(temp.path().join("src/run.graphql")),
+                codec: None,
+                output_codec: None,
+                limits: None,
+                input: None,
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -10,6 +10,7 @@ use bluejay_parser::ast::{
 use serde_json::Value;
 use std::collections::HashMap;
 
+/// The scale factor of an input, between 1.0 and 10.0.
 pub type ScaleLimitsAnalyzer<'a> = bluejay_validator::executable::operation::Orchestrator<
     'a,
     ExecutableDocument<'a>,
@@ -18,25 +19,34 @@ pub type ScaleLimitsAnalyzer<'a> = bluejay_validator::executable::operation::Orc
     ScaleLimits<'a>,
 >;
 
+/// The scale factor of an input without bounds, 0.0 when it has no rated fields. Limit profiles apply their own.
+pub type UnclampedScaleLimitsAnalyzer<'a> = bluejay_validator::executable::operation::Orchestrator<
+    'a,
+    ExecutableDocument<'a>,
+    SchemaDefinition<'a>,
+    serde_json::Map<String, serde_json::Value>,
+    ScaleLimits<'a, false>,
+>;
+
 #[derive(Hash, PartialEq, Eq, Debug)]
 struct PathWithIndex<'a> {
     path: Vec<&'a str>,
     index: usize,
 }
 
-pub struct ScaleLimits<'a> {
+pub struct ScaleLimits<'a, const CLAMPED: bool = true> {
     value_stack: Vec<Vec<&'a Value>>,
     path_stack: Vec<&'a str>,
     rates: HashMap<PathWithIndex<'a>, f64>,
 }
 
-impl<'a>
+impl<'a, const CLAMPED: bool>
     bluejay_validator::executable::operation::Visitor<
         'a,
         ExecutableDocument<'a>,
         SchemaDefinition<'a>,
         serde_json::Map<String, serde_json::Value>,
-    > for ScaleLimits<'a>
+    > for ScaleLimits<'a, CLAMPED>
 {
     type ExtraInfo = &'a Value;
 
@@ -118,13 +128,13 @@ impl<'a>
     }
 }
 
-impl<'a>
+impl<'a, const CLAMPED: bool>
     bluejay_validator::executable::operation::Analyzer<
         'a,
         ExecutableDocument<'a>,
         SchemaDefinition<'a>,
         serde_json::Map<String, serde_json::Value>,
-    > for ScaleLimits<'a>
+    > for ScaleLimits<'a, CLAMPED>
 {
     type Output = f64;
 
@@ -137,14 +147,18 @@ impl<'a>
             },
         );
 
-        normalized_rates
-            .into_values()
-            .fold(Self::MIN_SCALE_FACTOR, f64::max)
-            .clamp(Self::MIN_SCALE_FACTOR, Self::MAX_SCALE_FACTOR)
+        if CLAMPED {
+            normalized_rates
+                .into_values()
+                .fold(Self::MIN_SCALE_FACTOR, f64::max)
+                .clamp(Self::MIN_SCALE_FACTOR, Self::MAX_SCALE_FACTOR)
+        } else {
+            normalized_rates.into_values().fold(0.0, f64::max)
+        }
     }
 }
 
-impl<'a> ScaleLimits<'a> {
+impl<'a, const CLAMPED: bool> ScaleLimits<'a, CLAMPED> {
     const MIN_SCALE_FACTOR: f64 = 1.0;
     const MAX_SCALE_FACTOR: f64 = 10.0;
 
@@ -0,0 +1,11 @@
+(module
+  (memory (export "memory") 1)
+  (func $grow (param $pages i32)
+    ;; trap like an allocator would when memory can't be grown
+    (if (i32.eq (memory.grow (local.get $pages)) (i32.const -1))
+      (then unreachable)))
+  (func $_start
+    (call $grow (i32.const 1))
+    (call $grow (i32.const 2)))
+  (export "_start" (func $_start))
+)
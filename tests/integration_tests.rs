@@ -3,7 +3,7 @@ mod tests {
 
     use assert_cmd::prelude::*;
     use assert_fs::prelude::*;
-    use function_runner::function_run_result::FunctionRunResult;
+    use function_runner::function_run_result::{FunctionOutcome, FunctionRunResult};
     use predicates::prelude::*;
     use predicates::{prelude::predicate, str::contains};
     use serde_json::json;
@@ -124,7 +124,7 @@ mod tests {
             .arg(input_file.as_os_str());
         cmd.assert()
             .failure()
-            .stderr("Error: Couldn\'t load the Function \"test/file/doesnt/exist\": failed to read input file: test/file/doesnt/exist\n");
+            .stderr("Error: Couldn\'t load the Function \"test/file/doesnt/exist\": failed to read input file: test/file/doesnt/exist: No such file or directory (os error 2)\n");
 
         Ok(())
     }
@@ -173,6 +173,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn profile_rejects_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let (mut cmd, temp) = profile_base_cmd_in_temp_dir()?;
+        cmd.args(["--profile", "--timeout", "100"])
+            .assert()
+            .failure()
+            .stderr(contains("--timeout can't be used with profiling"));
+        temp.child("exit_code.perf")
+            .assert(predicate::path::missing());
+
+        Ok(())
+    }
+
     #[test]
     fn incorrect_input() -> Result<(), Box<dyn std::error::Error>> {
         let mut cmd = Command::cargo_bin("function-runner")?;
@@ -216,7 +229,445 @@ mod tests {
 
         cmd.assert()
             .failure()
-            .stderr(contains(" failed to find function export `_start`"));
+            .stderr(contains(" failed to find function export `_start`"))
+            .stderr(contains(
+                "use `function-runner inspect` to list the exports",
+            ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn enforce_instructions_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        let input_file = temp_input(json!({}))?;
+        cmd.args(["--function", "tests/fixtures/build/infinite_loop.wasm"])
+            .arg("--enforce-instructions-limit")
+            .arg("--input")
+            .arg(input_file.as_os_str());
+
+        cmd.assert()
+            .success()
+            .stdout(contains("looping forever"))
+            .stdout(contains("Instruction limit exceeded"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        let input_file = temp_input(json!({}))?;
+        cmd.args(["--function", "tests/fixtures/build/infinite_loop.wasm"])
+            .args(["--timeout", "100"])
+            .arg("--json")
+            .arg("--input")
+            .arg(input_file.as_os_str());
+
+        let output = cmd.output()?;
+        let result = serde_json::from_slice::<FunctionRunResult>(&output.stdout)?;
+        assert_eq!(
+            result.outcome,
+            FunctionOutcome::TimedOut { timeout_ms: 100 }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        let input_file = temp_input(json!({}))?;
+        cmd.args(["--function", "tests/fixtures/build/linear_memory.wasm"])
+            .arg("--input")
+            .arg(input_file.as_os_str());
+
+        cmd.assert()
+            .success()
+            .stdout(contains("Memory limit exceeded"));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/linear_memory.wasm"])
+            .args(["--memory-limit", "20000000"])
+            .arg("--input")
+            .arg(input_file.as_os_str());
+
+        cmd.assert()
+            .success()
+            .stdout(contains("Memory limit exceeded").not())
+            .stdout(contains("Linear Memory Usage: 12800KB"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn module_cache() -> Result<(), Box<dyn std::error::Error>> {
+        let cache_dir = assert_fs::TempDir::new()?;
+        let input_file = temp_input(json!({"code": 0}))?;
+
+        for _ in 0..2 {
+            let mut cmd = Command::cargo_bin("function-runner")?;
+            cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+                .arg("--cache-dir")
+                .arg(cache_dir.path())
+                .arg("--input")
+                .arg(input_file.as_os_str());
+            cmd.assert().success();
+        }
+        assert_eq!(std::fs::read_dir(cache_dir.path())?.count(), 1);
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--no-cache")
+            .arg("--input")
+            .arg(input_file.as_os_str());
+        cmd.assert().success();
+
+        let stale_entry = cache_dir.child("stale.cwasm");
+        stale_entry.write_str("")?;
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--cache-dir")
+            .arg(cache_dir.path())
+            .arg("--clear-cache")
+            .arg("--input")
+            .arg(input_file.as_os_str());
+        cmd.assert().success();
+        assert!(!stale_entry.exists());
+        assert_eq!(std::fs::read_dir(cache_dir.path())?.count(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shared_module_cache() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let cache_home = assert_fs::TempDir::new()?;
+        let cache_dir = cache_home.child("function-runner");
+        cache_dir.create_dir_all()?;
+        std::fs::set_permissions(cache_dir.path(), std::fs::Permissions::from_mode(0o777))?;
+        let input_file = temp_input(json!({"code": 0}))?;
+
+        // the default directory is skipped
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .env("XDG_CACHE_HOME", cache_home.path())
+            .arg("--input")
+            .arg(input_file.as_os_str());
+        cmd.assert()
+            .success()
+            .stderr(contains("warning: running without the module cache"));
+        assert_eq!(std::fs::read_dir(cache_dir.path())?.count(), 0);
+
+        // an explicit one fails
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--cache-dir")
+            .arg(cache_dir.path())
+            .arg("--input")
+            .arg(input_file.as_os_str());
+        cmd.assert()
+            .failure()
+            .stderr(contains("is writable by other users"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch() -> Result<(), Box<dyn std::error::Error>> {
+        let inputs = assert_fs::TempDir::new()?;
+        inputs
+            .child("a.json")
+            .write_str(&json!({"code": 0}).to_string())?;
+        inputs
+            .child("b.json")
+            .write_str(&json!({"code": 0}).to_string())?;
+        inputs.child("notes.txt").write_str("not an input")?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["batch", "--function", "tests/fixtures/build/exit_code.wasm"])
+            .args(["--jobs", "2"])
+            .arg(inputs.path());
+
+        cmd.assert()
+            .success()
+            .stdout(contains("a.json"))
+            .stdout(contains("b.json"))
+            .stdout(contains("notes.txt").not())
+            .stdout(contains("2/2 passed"))
+            .stderr(contains("warning: skipping").and(contains("notes.txt")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_ndjson_with_failures() -> Result<(), Box<dyn std::error::Error>> {
+        let passing = temp_input(json!({"code": 0}))?;
+        let failing = temp_input(json!({"code": 1}))?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["batch", "--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--ndjson")
+            .arg(passing.path())
+            .arg("tests/fixtures/input/invalid_json.json")
+            .arg(failing.path());
+
+        let output = cmd.output()?;
+        assert!(!output.status.success());
+
+        let lines = String::from_utf8(output.stdout)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, _>>()?;
+        let passed: Vec<&serde_json::Value> = lines.iter().map(|line| &line["passed"]).collect();
+        assert_eq!(passed, [true, false, false]);
+        assert!(lines[1]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid input JSON"));
+        assert_eq!(lines[2]["result"]["output"], json!({"exit": 1}));
+
+        Ok(())
+    }
+
+    #[test]
+    fn enforce_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let input_file = temp_input(json!({}))?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args([
+            "--function",
+            "tests/fixtures/build/log_truncation_function.wasm",
+        ])
+        .args(["--json", "--enforce-limits"])
+        .arg("--input")
+        .arg(input_file.as_os_str());
+
+        let output = cmd.output()?;
+        assert_eq!(output.status.code(), Some(3));
+        assert!(String::from_utf8(output.stderr)?
+            .contains("Limit exceeded: logs length exceeded: used 6000, limit is 1000"));
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(
+            result["violations"],
+            json!([{"resource": "logs", "used": 6000, "limit": 1000}])
+        );
+
+        let input_file = temp_input(json!({"code": 0}))?;
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--enforce-limits")
+            .arg("--input")
+            .arg(input_file.as_os_str());
+        cmd.assert().success();
+
+        Ok(())
+    }
+
+    #[test]
+    fn limit_profile() -> Result<(), Box<dyn std::error::Error>> {
+        let input_file = temp_input(json!({"code": 0}))?;
+        let profile = assert_fs::NamedTempFile::new("limits.toml")?;
+        profile.write_str("output_size = 5\ninstructions = 20_000_000\n")?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .args(["--json", "--enforce-limits", "--limits"])
+            .arg(profile.path())
+            .arg("--input")
+            .arg(input_file.as_os_str());
+
+        let output = cmd.output()?;
+        assert_eq!(output.status.code(), Some(3));
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(result["limits"]["instructions"], 20_000_000);
+        assert_eq!(
+            result["violations"],
+            json!([{"resource": "output_size", "used": 10, "limit": 5}])
+        );
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .args(["--limits", "payments"])
+            .arg("--input")
+            .arg(input_file.as_os_str());
+        cmd.assert().failure().stderr(contains(
+            "Unknown limit profile \"payments\", expected one of default, unscaled",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_target_from_project_config() -> Result<(), Box<dyn std::error::Error>> {
+        let project = assert_fs::TempDir::new()?;
+        project
+            .child("build/exports.wasm")
+            .write_file(std::path::Path::new("tests/fixtures/build/exports.wasm"))?;
+        project.child("function-runner.toml").write_str(
+            r#"
+            function = "build/exports.wasm"
+
+            [targets."purchase.product-discount.run"]
+            export = "export1"
+
+            [targets."purchase.missing.run"]
+            export = "missing"
+            "#,
+        )?;
+        project.child("input.json").write_str("{}")?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.current_dir(project.path())
+            .args(["run", "--target", "purchase.product-discount.run"])
+            .args(["--input", "input.json"]);
+        cmd.assert().success().stdout(contains("export1"));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.current_dir(project.path())
+            .args(["--target", "purchase.product-discount.run"])
+            .args(["--export", "_start", "--input", "input.json"]);
+        cmd.assert()
+            .failure()
+            .stderr(contains("failed to find function export `_start`"));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.arg("run")
+            .arg("--config")
+            .arg(project.child("function-runner.toml").path())
+            .args(["--target", "cart.transform.run"])
+            .arg("--input")
+            .arg(project.child("input.json").path());
+        cmd.assert().failure().stderr(contains(
+            "expected one of: purchase.missing.run, purchase.product-discount.run",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_all_targets() -> Result<(), Box<dyn std::error::Error>> {
+        let project = assert_fs::TempDir::new()?;
+        project.child("function-runner.toml").write_str(&format!(
+            r#"
+            function = {:?}
+
+            [targets."purchase.product-discount.run"]
+            input = "inputs/product.json"
+
+            [targets."purchase.order-discount.run"]
+            export = "run_order"
+            input = "inputs/order.json"
+
+            [targets."purchase.shipping-discount.run"]
+            "#,
+            std::fs::canonicalize("tests/fixtures/build/exit_code.wasm")?
+        ))?;
+        project
+            .child("inputs/product.json")
+            .write_str(&json!({"code": 0}).to_string())?;
+        project.child("inputs/order.json").write_str("{}")?;
+        project
+            .child("shipping.json")
+            .write_str(&json!({"code": 0}).to_string())?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.current_dir(project.path())
+            .args(["targets", "--ndjson"])
+            .args(["--input", "purchase.shipping-discount.run=shipping.json"]);
+
+        let output = cmd.output()?;
+        assert!(!output.status.success());
+        let lines = String::from_utf8(output.stdout)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, _>>()?;
+        let names: Vec<&serde_json::Value> = lines.iter().map(|line| &line["name"]).collect();
+        assert_eq!(
+            names,
+            [
+                "purchase.order-discount.run",
+                "purchase.product-discount.run",
+                "purchase.shipping-discount.run"
+            ]
+        );
+        let passed: Vec<&serde_json::Value> = lines.iter().map(|line| &line["passed"]).collect();
+        assert_eq!(passed, [false, true, true]);
+        assert!(lines[0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("failed to find function export `run_order`"));
+        assert_eq!(lines[1]["result"]["output"], json!({"exit": 0}));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.current_dir(project.path()).args([
+            "targets",
+            "--target",
+            "purchase.shipping-discount.run",
+        ]);
+        cmd.assert()
+            .failure()
+            .stdout(contains(
+                "No input for target \"purchase.shipping-discount.run\"",
+            ))
+            .stdout(contains("0/1 passed"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cases_with_junit_report_and_update() -> Result<(), Box<dyn std::error::Error>> {
+        let cases = assert_fs::TempDir::new()?;
+        for (name, code, expected) in [("matching", 0, 0), ("mismatching", 1, 0)] {
+            let case = cases.child(name);
+            case.child("input.json")
+                .write_str(&json!({"code": code}).to_string())?;
+            case.child("expected.json")
+                .write_str(&json!({"exit": expected}).to_string())?;
+        }
+        let report = cases.child("report.xml");
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["test", "--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--junit")
+            .arg(report.path())
+            .arg(cases.path());
+        cmd.assert()
+            .failure()
+            .stdout(contains("pass     matching"))
+            .stdout(contains(
+                "ERROR    mismatching: Function exited with code 1",
+            ))
+            .stdout(contains("1/2 passed"));
+        report.assert(contains(r#"tests="2" failures="0" errors="1""#));
+
+        cases
+            .child("mismatching/input.json")
+            .write_str(&json!({"code": 0}).to_string())?;
+        cases
+            .child("mismatching/expected.json")
+            .write_str(&json!({"exit": 1}).to_string())?;
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["test", "--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg(cases.path());
+        cmd.assert()
+            .failure()
+            .stdout(contains("FAIL     mismatching"))
+            .stdout(contains("    /exit: expected 1, got 0"));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["test", "--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--update")
+            .arg(cases.path());
+        cmd.assert()
+            .success()
+            .stdout(contains("updated  mismatching"));
+        let updated: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(
+            cases.child("mismatching/expected.json").path(),
+        )?)?;
+        assert_eq!(updated, json!({"exit": 0}));
 
         Ok(())
     }
@@ -331,4 +782,181 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn inspect_exports() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["inspect", "--function", "tests/fixtures/build/exports.wasm"]);
+        cmd.assert()
+            .success()
+            .stdout(contains("export1: func () -> ()"))
+            .stdout(contains("fd_write: func (i32, i32, i32, i32) -> i32"))
+            .stdout(contains("\"_start\" isn't an exported function"));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args([
+            "inspect",
+            "--json",
+            "--function",
+            "tests/fixtures/build/js_function.wasm",
+        ]);
+        let output = cmd.output()?;
+        assert!(output.status.success());
+        let info: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(info["exports"][0]["signature"], "() -> ()");
+        assert_eq!(
+            info["imports"]["javy_quickjs_provider_v1"]["provider"],
+            true
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_module() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["check", "--function", "tests/fixtures/build/exports.wasm"])
+            .args(["--export", "export1"]);
+        cmd.assert()
+            .success()
+            .stdout(contains("0 errors, 0 warnings"));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args([
+            "check",
+            "--function",
+            "tests/fixtures/build/linear_memory.wasm",
+        ]);
+        cmd.assert()
+            .failure()
+            .stdout(contains(
+                "memory 0 starts at 13107200 bytes, above the 10000000 bytes linear memory limit",
+            ))
+            .stdout(contains("1 error, 0 warnings"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn messagepack_output_codec() -> Result<(), Box<dyn std::error::Error>> {
+        let function = assert_fs::NamedTempFile::new("messagepack.wat")?;
+        function.write_str(
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\81\a1\61\01")
+                (func (export "_start")
+                    (i32.store (i32.const 0) (i32.const 16))
+                    (i32.store (i32.const 4) (i32.const 4))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))
+            )"#,
+        )?;
+        let input = assert_fs::NamedTempFile::new("input.json")?;
+        input.write_str("{}")?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", function.path().to_str().unwrap()])
+            .args(["--input", input.path().to_str().unwrap()])
+            .args(["--output-codec", "messagepack", "--json"]);
+        let output = cmd.output()?;
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(result["output"], json!({"a": 1}));
+        assert_eq!(result["input_codec"], "json");
+        assert_eq!(result["output_codec"], "messagepack");
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", function.path().to_str().unwrap()])
+            .args(["--input", input.path().to_str().unwrap()]);
+        cmd.assert()
+            .success()
+            .stdout(contains("JSON Error"))
+            .stdout(contains("81 a1 61 01"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn auto_codec() -> Result<(), Box<dyn std::error::Error>> {
+        let function = assert_fs::NamedTempFile::new("rmp.wat")?;
+        function.write_str(
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\81\a1\61\01")
+                (func $rmp_serde::decode::from_slice)
+                (func (export "_start")
+                    (call $rmp_serde::decode::from_slice)
+                    (i32.store (i32.const 0) (i32.const 16))
+                    (i32.store (i32.const 4) (i32.const 4))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))
+            )"#,
+        )?;
+        let input = assert_fs::NamedTempFile::new("input.json")?;
+        input.write_str(r#"{"b": true}"#)?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", function.path().to_str().unwrap()])
+            .args(["--input", input.path().to_str().unwrap()])
+            .args(["--codec", "auto", "--json"]);
+        let output = cmd.output()?;
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(result["input"], json!({"b": true}));
+        assert_eq!(result["input_codec"], "messagepack");
+        assert_eq!(result["output"], json!({"a": 1}));
+        assert_eq!(result["output_codec"], "messagepack");
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", function.path().to_str().unwrap()])
+            .args(["--input", input.path().to_str().unwrap()])
+            .args(["--codec", "auto"]);
+        cmd.assert()
+            .success()
+            .stdout(contains("\"a\": 1\n}\n(messagepack)"));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "missing.wasm"])
+            .args(["--input", input.path().to_str().unwrap()])
+            .args(["--codec", "auto"]);
+        cmd.assert().failure().stderr(contains(
+            "Couldn't load the Function \"missing.wasm\": No such file or directory",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn patch_and_set_input() -> Result<(), Box<dyn std::error::Error>> {
+        let input_file = temp_input(json!({"code": 0, "cart": {"lines": [{"quantity": 1}]}}))?;
+        let patch = assert_fs::NamedTempFile::new("patch.json")?;
+        patch.write_str(
+            &json!([{"op": "add", "path": "/cart/lines/-", "value": {"quantity": 2}}]).to_string(),
+        )?;
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--input")
+            .arg(input_file.as_os_str())
+            .arg("--patch")
+            .arg(patch.path())
+            .args(["--set", "cart.lines.1.quantity=5", "--set", "/code=1"])
+            .arg("--json");
+        let output = cmd.output()?;
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(
+            result["input"],
+            json!({"code": 1, "cart": {"lines": [{"quantity": 1}, {"quantity": 5}]}})
+        );
+        assert_eq!(result["outcome"], json!({"status": "exited", "code": 1}));
+
+        let mut cmd = Command::cargo_bin("function-runner")?;
+        cmd.args(["--function", "tests/fixtures/build/exit_code.wasm"])
+            .arg("--input")
+            .arg(input_file.as_os_str())
+            .args(["--set", "cart.lines.3.quantity=5"]);
+        cmd.assert().failure().stderr(contains(
+            "Couldn't set \"/cart/lines/3/quantity\": Invalid array index \"3\"",
+        ));
+
+        Ok(())
+    }
 }